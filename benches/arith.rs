@@ -0,0 +1,34 @@
+use std::ops::Deref;
+use std::time::Instant;
+
+use ak::k::{K, K0};
+
+// milliseconds per 10M item add, make bench runs this with and without the parallel feature
+fn main() {
+    let n = 10_000_000;
+    let ints = K::from((0..n as i64).collect::<Vec<_>>());
+    let floats = K::from((0..n).map(|i| i as f64 / 3.0).collect::<Vec<_>>());
+    for (name, x) in [("int", ints), ("float", floats)] {
+        let runs = 10;
+        let start = Instant::now();
+        let mut sum = K::from(0i64);
+        for _ in 0..runs {
+            sum = (&x + &x).expect("add");
+        }
+        println!(
+            "{} add: {:.1}ms",
+            name,
+            start.elapsed().as_secs_f64() * 1000.0 / runs as f64
+        );
+        // the same bits as adding one item at a time
+        let same = match (sum.deref(), x.deref()) {
+            (K0::IntList(s), K0::IntList(x)) => s.iter().zip(x).all(|(&s, &i)| s == i + i),
+            (K0::FloatList(s), K0::FloatList(x)) => s
+                .iter()
+                .zip(x)
+                .all(|(s, &i)| s.to_bits() == (i + i).to_bits()),
+            _ => false,
+        };
+        assert!(same, "{} add differs from the serial result", name);
+    }
+}
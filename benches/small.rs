@@ -0,0 +1,40 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+// counts heap allocations, to compare builds with and without the smallvec feature
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// allocations and milliseconds per evaluation of expressions building lots of short lists
+fn main() {
+    for src in &[&b"+/!1000"[..], b"{x+1 2 3}'!1000", b"{(x;x+1)}'!1000"] {
+        let runs = 100;
+        let allocs = ALLOCS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        for _ in 0..runs {
+            ak::eval(src).expect("eval");
+        }
+        println!(
+            "{}: {} allocations, {:.3}ms",
+            String::from_utf8_lossy(src),
+            (ALLOCS.load(Ordering::Relaxed) - allocs) / runs,
+            start.elapsed().as_secs_f64() * 1000.0 / runs as f64
+        );
+    }
+}
@@ -1,14 +1,59 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fs;
 use std::lazy::SyncLazy;
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 
+use crate::error::RuntimeErrorCode;
+use crate::k::ser::{read_len, read_sym, write_len, write_sym};
 use crate::k::K;
 use crate::sym::Sym;
 
+const WORKSPACE_MAGIC: &[u8] = b"akws";
+const WORKSPACE_VERSION: u8 = 1;
+// nested function calls allowed before a call fails instead of overflowing the stack
+const MAX_DEPTH: usize = 1000;
+
 static GLOBAL_ENV: SyncLazy<RwLock<Environment>> =
     SyncLazy::new(|| RwLock::new(Environment::new()));
 
+thread_local! {
+    // local variables of the functions being called, innermost last
+    static FRAMES: RefCell<Vec<HashMap<Sym, K>>> = const { RefCell::new(Vec::new()) };
+}
+
+// start a function call with its arguments as locals
+pub fn push_frame(locals: HashMap<Sym, K>) -> Result<(), RuntimeErrorCode> {
+    FRAMES.with(|f| {
+        let mut frames = f.borrow_mut();
+        if frames.len() >= MAX_DEPTH {
+            return Err(RuntimeErrorCode::Stack);
+        }
+        frames.push(locals);
+        Ok(())
+    })
+}
+
+pub fn pop_frame() {
+    FRAMES.with(|f| f.borrow_mut().pop());
+}
+
+// assignments inside a function call define locals of that call
 pub fn define_variable(name: Sym, value: &K) {
+    let local = FRAMES.with(|f| match f.borrow_mut().last_mut() {
+        Some(locals) => {
+            locals.insert(name, value.clone());
+            true
+        }
+        None => false,
+    });
+    if !local {
+        define_global(name, value);
+    }
+}
+
+pub fn define_global(name: Sym, value: &K) {
     GLOBAL_ENV
         .write()
         .expect("poisoned rwlock")
@@ -21,31 +66,218 @@ pub fn print_variable_rcs() {
     }
 }
 
+// locals of the innermost function call shadow globals
 pub fn get_variable(name: Sym) -> Option<K> {
-    GLOBAL_ENV
-        .read()
-        .expect("poisoned rwlock")
-        .get(name)
-        .cloned()
+    FRAMES
+        .with(|f| {
+            f.borrow()
+                .last()
+                .and_then(|locals| locals.get(&name).cloned())
+        })
+        .or_else(|| {
+            GLOBAL_ENV
+                .read()
+                .expect("poisoned rwlock")
+                .get(name)
+                .cloned()
+        })
+}
+
+// current context (namespace), None is the root
+pub fn get_context() -> Option<Sym> {
+    GLOBAL_ENV.read().expect("poisoned rwlock").context
+}
+
+pub fn set_context(context: Option<Sym>) {
+    GLOBAL_ENV.write().expect("poisoned rwlock").context = context;
+}
+
+// names of all global variables, sorted
+pub fn global_names() -> Vec<Sym> {
+    GLOBAL_ENV.read().expect("poisoned rwlock").names()
+}
+
+// write all global variables to a file, returning their names
+pub fn save_workspace(path: &Path) -> Result<Vec<Sym>, RuntimeErrorCode> {
+    GLOBAL_ENV.read().expect("poisoned rwlock").save(path)
+}
+
+// read global variables from a file saved by save_workspace, returning their names
+pub fn load_workspace(path: &Path) -> Result<Vec<Sym>, RuntimeErrorCode> {
+    GLOBAL_ENV.write().expect("poisoned rwlock").load(path)
 }
 
 #[derive(Default)]
 struct Environment {
     map: HashMap<Sym, K>,
+    context: Option<Sym>,
 }
 
 impl Environment {
     fn new() -> Self {
         Self {
             map: HashMap::new(),
+            context: None,
+        }
+    }
+
+    // names starting with a . are fully qualified, others live in the current context
+    fn qualify(&self, name: Sym) -> Sym {
+        match self.context {
+            Some(context) if !name.as_bytes().starts_with(b".") => {
+                Sym::new(&[context.as_bytes(), b".", name.as_bytes()].concat())
+            }
+            _ => name,
         }
     }
 
     fn define(&mut self, name: Sym, value: &K) {
-        self.map.insert(name, value.clone());
+        self.map.insert(self.qualify(name), value.clone());
     }
 
+    // unqualified names fall back to the root context
     fn get(&self, name: Sym) -> Option<&K> {
-        self.map.get(&name)
+        self.map
+            .get(&self.qualify(name))
+            .or_else(|| self.map.get(&name))
+    }
+
+    fn names(&self) -> Vec<Sym> {
+        let mut names = self.map.keys().copied().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    fn save(&self, path: &Path) -> Result<Vec<Sym>, RuntimeErrorCode> {
+        let names = self.names();
+        let mut buf = Vec::from(WORKSPACE_MAGIC);
+        buf.push(WORKSPACE_VERSION);
+        write_len(&mut buf, names.len());
+        for name in &names {
+            write_sym(&mut buf, *name);
+            self.map[name].write_to(&mut buf);
+        }
+        fs::write(path, buf)?;
+        Ok(names)
+    }
+
+    // names are stored fully qualified, so they are restored as is
+    fn load(&mut self, path: &Path) -> Result<Vec<Sym>, RuntimeErrorCode> {
+        let bytes = fs::read(path)?;
+        let mut buf = match bytes.strip_prefix(WORKSPACE_MAGIC) {
+            Some(rest) => rest,
+            None => return Err(RuntimeErrorCode::Format),
+        };
+        match buf.split_first() {
+            Some((&WORKSPACE_VERSION, rest)) => buf = rest,
+            Some((&version, _)) => return Err(RuntimeErrorCode::Version(version)),
+            None => return Err(RuntimeErrorCode::Format),
+        }
+        let mut vars = Vec::new();
+        for _ in 0..read_len(&mut buf)? {
+            let name = read_sym(&mut buf)?;
+            vars.push((name, K::read_from(&mut buf)?));
+        }
+        if !buf.is_empty() {
+            return Err(RuntimeErrorCode::Format);
+        }
+        Ok(vars
+            .into_iter()
+            .map(|(name, value)| {
+                self.map.insert(name, value);
+                name
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::Environment;
+    use crate::error::RuntimeErrorCode;
+    use crate::k::{list, K, K0};
+    use crate::sym::Sym;
+
+    #[test]
+    fn context_test() {
+        let mut env = Environment::new();
+        let two = Sym::new(b"two");
+        let math_two = Sym::new(b".math.two");
+        let one = Sym::new(b"one");
+
+        env.define(one, &K::from(1.0));
+        env.context = Some(Sym::new(b".math"));
+        env.define(two, &K::from(2.0));
+        assert_eq!(env.get(two).unwrap().to_string(), "2");
+        assert_eq!(env.get(math_two).unwrap().to_string(), "2");
+        assert_eq!(env.get(one).unwrap().to_string(), "1");
+
+        env.context = None;
+        assert!(env.get(two).is_none());
+        assert_eq!(env.get(math_two).unwrap().to_string(), "2");
+    }
+
+    #[test]
+    fn workspace_test() {
+        let path = env::temp_dir().join(format!("ak-workspace-test-{}", process::id()));
+        let mut env = Environment::new();
+        env.define(Sym::new(b"a"), &K::from(vec![1i64, 2, 3]));
+        env.define(
+            Sym::new(b"b"),
+            &K::from(vec![Sym::new(b"x"), Sym::new(b"y")]),
+        );
+        env.define(Sym::new(b".ctx.c"), &K::from(b'c'));
+        env.define(
+            Sym::new(b"d"),
+            &K0::GenList(list![K::from(1.5), K::from(b"str".to_vec())]).into(),
+        );
+        env.define(
+            Sym::new(b"e"),
+            &K::from(vec![Sym::new(b"k")])
+                .dict(&K::from(vec![2i64]))
+                .unwrap(),
+        );
+        assert_eq!(env.save(&path).unwrap().len(), 5);
+
+        let mut fresh = Environment::new();
+        assert_eq!(fresh.load(&path).unwrap().len(), 5);
+        for (name, value) in &[
+            (&b"a"[..], "1 2 3"),
+            (b"b", "`x`y"),
+            (b".ctx.c", "'c'"),
+            (b"d", "(1.5;\"str\")"),
+            (b"e", "k| 2"),
+        ] {
+            assert_eq!(fresh.get(Sym::new(name)).unwrap().to_string(), *value);
+        }
+
+        fs::write(&path, b"akws\x02").unwrap();
+        assert!(matches!(
+            fresh.load(&path),
+            Err(RuntimeErrorCode::Version(2))
+        ));
+        fs::write(&path, b"not a workspace").unwrap();
+        assert!(matches!(fresh.load(&path), Err(RuntimeErrorCode::Format)));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn names_test() {
+        let mut env = Environment::new();
+        assert!(env.names().is_empty());
+        env.define(Sym::new(b"zeta"), &K::from(1i64));
+        env.define(Sym::new(b"alpha"), &K::from(2i64));
+        env.define(Sym::new(b"alpha"), &K::from(3i64));
+        env.define(Sym::new(b".ctx.mid"), &K::from(4i64));
+        let names = env
+            .names()
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["`.ctx.mid", "`alpha", "`zeta"]);
     }
 }
@@ -1,10 +1,36 @@
 use std::fmt::Debug;
+use std::io;
 use std::num::{ParseFloatError, ParseIntError};
 
+use crate::k::Verb;
+use crate::sym::Sym;
+
 #[derive(Debug)]
 pub struct KError<T: Debug> {
     pub location: usize,
+    // end of the offending source, past location, when known
+    pub end: Option<usize>,
     pub code: T,
+    // the verb that raised the error and the number of arguments it was given, when known
+    pub verb: Option<(Verb, usize)>,
+}
+
+impl<T: Debug> KError<T> {
+    // the code, followed by the verb that raised it: "Nyi: monadic ^"
+    pub fn message(&self) -> String {
+        match self.verb {
+            Some((verb, n)) => {
+                let arity = match n {
+                    1 => "monadic",
+                    2 => "dyadic",
+                    3 => "triadic",
+                    _ => "polyadic",
+                };
+                format!("{:?}: {} {}", self.code, arity, verb.symbol())
+            }
+            None => format!("{:?}", self.code),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -15,6 +41,7 @@ pub enum LexerErrorCode {
     UnrecognizedEscape,
     UnrecognizedToken,
     InvalidNumber,
+    SymbolTableFull,
     ParseFloatError(ParseFloatError),
     ParseIntError(ParseIntError),
 }
@@ -35,6 +62,8 @@ impl From<ParseIntError> for LexerErrorCode {
 pub enum ParserErrorCode {
     UnclosedParens,
     UnclosedBrackets,
+    UnclosedBraces,
+    DuplicateParameter,
     UnexpectedToken,
 }
 
@@ -44,17 +73,82 @@ pub enum RuntimeErrorCode {
     Nyi,
     Rank,
     Type,
+    Index,
+    NonNumeric,
+    // an argument of the right type outside the values a verb accepts
+    Domain,
     NameExpectedOnLhs,
     ExpressionExpected,
-    UndefinedVariable,
+    UndefinedVariable(Sym),
+    Io(io::Error),
+    Format,
+    Version(u8),
+    Restricted,
+    // function calls nested deeper than the interpreter allows
+    Stack,
+    // no room left to intern a new symbol
+    SymbolTableFull,
+    Lexer(LexerErrorCode),
+    Parser(ParserErrorCode),
+}
+
+impl RuntimeErrorCode {
+    // what a trap handler is given: the variant, with the name for an undefined variable
+    pub fn name(&self) -> String {
+        let name = match self {
+            Self::Length => "Length",
+            Self::Nyi => "Nyi",
+            Self::Rank => "Rank",
+            Self::Type => "Type",
+            Self::Index => "Index",
+            Self::NonNumeric => "NonNumeric",
+            Self::Domain => "Domain",
+            Self::NameExpectedOnLhs => "NameExpectedOnLhs",
+            Self::ExpressionExpected => "ExpressionExpected",
+            Self::UndefinedVariable(name) => {
+                return format!(
+                    "UndefinedVariable: {}",
+                    String::from_utf8_lossy(name.as_bytes())
+                )
+            }
+            Self::Io(_) => "Io",
+            Self::Format => "Format",
+            Self::Version(_) => "Version",
+            Self::Restricted => "Restricted",
+            Self::Stack => "Stack",
+            Self::SymbolTableFull => "SymbolTableFull",
+            Self::Lexer(_) => "Lexer",
+            Self::Parser(_) => "Parser",
+        };
+        name.to_string()
+    }
+}
+
+impl From<io::Error> for RuntimeErrorCode {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
 }
 
 pub type LexerError = KError<LexerErrorCode>;
 pub type ParserError = KError<ParserErrorCode>;
 pub type RuntimeError = KError<RuntimeErrorCode>;
 
+// an error from any stage of evaluating source text
+#[derive(Debug)]
+pub enum EvalError {
+    Lexer(LexerError),
+    Parser(ParserError),
+    Runtime(RuntimeError),
+}
+
 impl RuntimeError {
     pub fn new(location: usize, code: RuntimeErrorCode) -> Self {
-        Self { location, code }
+        Self {
+            location,
+            end: None,
+            verb: None,
+            code,
+        }
     }
 }
@@ -1,12 +1,482 @@
-use std::collections::VecDeque;
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::iter;
+use std::mem;
 use std::ops::Deref;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 
-use crate::environ::{define_variable, get_variable};
+use crate::environ::{
+    define_global, define_variable, get_variable, load_workspace, pop_frame, push_frame,
+    save_workspace,
+};
 use crate::error::{RuntimeError, RuntimeErrorCode};
-use crate::k::{Verb, K, K0};
-use crate::parser::ASTNode;
+use crate::k::{list, sanitize_names, to_list, Adverb, Builtin, Func, Verb, INF_INT, K, K0};
+use crate::parser::{ASTNode, Parser};
 use crate::span::Spanned;
 use crate::sym::Sym;
+use crate::tok::Tokenizer;
+use crate::util::TrimStart;
+
+thread_local! {
+    // set while evaluating untrusted input with reval, disables io builtins
+    static RESTRICTED: Cell<bool> = const { Cell::new(false) };
+    // set while evaluating config values, additionally disables assignment to globals
+    static READONLY: Cell<bool> = const { Cell::new(false) };
+}
+
+// tokenize, parse and interpret a nested program
+fn eval(src: &[u8]) -> Result<K, RuntimeErrorCode> {
+    let tokens = Tokenizer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| RuntimeErrorCode::Lexer(e.code))?;
+    match Parser::new(src, tokens).parse() {
+        Ok(Some(ast)) => ast.interpret().map_err(|e| e.code),
+        Ok(None) => Ok(K0::Nil.into()),
+        Err(e) => Err(RuntimeErrorCode::Parser(e.code)),
+    }
+}
+
+const FS_CHUNK_SIZE: usize = 128 << 10;
+
+// call f with chunks of about chunk_size bytes worth of lines (without line terminators) from
+// a file, returning the number of lines read
+fn read_chunks<F>(path: &Path, chunk_size: usize, mut f: F) -> Result<i64, RuntimeError>
+where
+    F: FnMut(K) -> Result<K, RuntimeError>,
+{
+    let io_error = |e| RuntimeError::new(0, RuntimeErrorCode::Io(e));
+    let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+    let mut count = 0;
+    let mut chunk = Vec::new();
+    let mut chunk_bytes = 0;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line).map_err(io_error)?;
+        if n > 0 {
+            chunk_bytes += n;
+            count += 1;
+            let end = line.iter().rposition(|x| !matches!(x, b'\n' | b'\r'));
+            chunk.push(K0::CharList(line[..end.map_or(0, |x| x + 1)].into()).into());
+        }
+        if (n == 0 && !chunk.is_empty()) || chunk_bytes >= chunk_size {
+            f(K0::GenList(to_list(mem::take(&mut chunk))).into())?;
+            chunk_bytes = 0;
+        }
+        if n == 0 {
+            return Ok(count);
+        }
+    }
+}
+
+fn reval(src: &[u8]) -> Result<K, RuntimeErrorCode> {
+    let restricted = RESTRICTED.with(|r| r.replace(true));
+    let res = eval(src);
+    RESTRICTED.with(|r| r.set(restricted));
+    res
+}
+
+// name and rhs of a name:value expression
+fn assignment(ast: ASTNode) -> Option<(Sym, ASTNode)> {
+    match ast {
+        ASTNode::Apply(Spanned(_, _, (verb, args))) => match (*verb, <[_; 2]>::try_from(args)) {
+            (ASTNode::Expr(Spanned(_, _, v)), Ok([Some(ASTNode::Expr(lhs)), Some(rhs)])) => {
+                match (v.deref(), lhs.2.deref()) {
+                    (K0::Verb(Verb::Colon), K0::Name(name)) => Some((*name, rhs)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// read a file of name:value lines (blank lines and / comments are skipped), evaluating each
+// value restricted and without side effects, into a dictionary of names to values
+fn load_config(path: &Path) -> Result<K, RuntimeError> {
+    let src = fs::read(path).map_err(|e| RuntimeError::new(0, RuntimeErrorCode::Io(e)))?;
+    let mut names = Vec::new();
+    let mut values = Vec::new();
+    let mut offset = 0;
+    for line in src.split(|x| *x == b'\n') {
+        let start = offset;
+        offset += line.len() + 1;
+        if matches!(line.trim_start().first(), None | Some(b'/')) {
+            continue;
+        }
+        let error = |e: RuntimeErrorCode, location| RuntimeError::new(start + location, e);
+        let tokens = Tokenizer::new(line)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| error(RuntimeErrorCode::Lexer(e.code), e.location))?;
+        let (name, rhs) = match Parser::new(line, tokens).parse() {
+            Ok(Some(ast)) => {
+                let s = ast.start();
+                assignment(ast).ok_or_else(|| error(RuntimeErrorCode::NameExpectedOnLhs, s))?
+            }
+            Ok(None) => return Err(error(RuntimeErrorCode::NameExpectedOnLhs, 0)),
+            Err(e) => return Err(error(RuntimeErrorCode::Parser(e.code), e.location)),
+        };
+        let restricted = RESTRICTED.with(|r| r.replace(true));
+        let readonly = READONLY.with(|r| r.replace(true));
+        let value = rhs.interpret();
+        RESTRICTED.with(|r| r.set(restricted));
+        READONLY.with(|r| r.set(readonly));
+        names.push(name);
+        values.push(value.map_err(|e| error(e.code, e.location))?);
+    }
+    Ok(K0::Dict(names.into(), values.into()).into())
+}
+
+// call a function with its arguments bound to its parameters, or x, y and z
+fn call(func: &Func, args: &[K], start: usize) -> Result<K, RuntimeError> {
+    let params = match &func.params {
+        Some(params) => params.clone(),
+        None => vec![Sym::new(b"x"), Sym::new(b"y"), Sym::new(b"z")],
+    };
+    // f[] passes a single nil to a niladic function
+    if args.len() > func.valence().max(1) {
+        return Err(RuntimeError::new(start, RuntimeErrorCode::Rank));
+    }
+    let locals = params
+        .into_iter()
+        .zip(args.iter().cloned())
+        .collect::<HashMap<_, _>>();
+    push_frame(locals).map_err(|e| RuntimeError::new(start, e))?;
+    let res = func
+        .body
+        .iter()
+        .try_fold(K0::Nil.into(), |_, ast| match ast {
+            Some(ast) => ast.clone().interpret(),
+            None => Ok(K0::Nil.into()),
+        });
+    pop_frame();
+    res
+}
+
+// apply f to arguments nobody else needs, so arithmetic can reuse the buffer of a list
+fn apply_owned(f: &K, args: Vec<K>, start: usize) -> Result<K, RuntimeError> {
+    match <[K; 2]>::try_from(args) {
+        Ok([x, y]) => apply_dyad(f, x, y, start),
+        Err(args) => f.apply(&args, start),
+    }
+}
+
+fn apply_dyad(f: &K, x: K, y: K, start: usize) -> Result<K, RuntimeError> {
+    let op: fn(K, K) -> Result<K, RuntimeErrorCode> = match f.deref() {
+        K0::Verb(Verb::Plus) => |x, y| x + y,
+        K0::Verb(Verb::Minus) => |x, y| x - y,
+        K0::Verb(Verb::Star) => |x, y| x * y,
+        K0::Verb(Verb::Percent) => |x, y| x / y,
+        _ => return f.apply(&[x, y], start),
+    };
+    op(x, y).map_err(|e| verb_error(f, 2, start, RuntimeError::new(start, e)))
+}
+
+// name the verb and its argument count in the Nyi, Type and Rank errors it raised itself
+fn verb_error(f: &K, n: usize, start: usize, e: RuntimeError) -> RuntimeError {
+    match (f.deref(), &e.code) {
+        (K0::Verb(v), RuntimeErrorCode::Nyi | RuntimeErrorCode::Type | RuntimeErrorCode::Rank)
+            if e.location == start && e.verb.is_none() =>
+        {
+            RuntimeError {
+                verb: Some((*v, n)),
+                ..e
+            }
+        }
+        _ => e,
+    }
+}
+
+// value of f/x for an empty x
+fn identity(f: &K) -> Option<K> {
+    match f.deref() {
+        K0::Verb(Verb::Plus) => Some(0i64.into()),
+        K0::Verb(Verb::Star) => Some(1i64.into()),
+        K0::Verb(Verb::And) => Some(INF_INT.into()),
+        K0::Verb(Verb::Pipe) => Some((-INF_INT).into()),
+        K0::Verb(Verb::Comma) => Some(K0::GenList(list![]).into()),
+        _ => None,
+    }
+}
+
+// fold the items of x with the dyadic f from left to right, starting from the seed if any, and
+// call step with each accumulated value of a list x
+fn fold<F>(f: &K, seed: Option<&K>, x: &K, start: usize, mut step: F) -> Result<K, RuntimeError>
+where
+    F: FnMut(&K),
+{
+    let items = match (x.items(), seed) {
+        (Some(items), _) => items,
+        (None, Some(seed)) => return f.apply(&[seed.clone(), x.clone()], start),
+        (None, None) => return Ok(x.clone()),
+    };
+    let (first, rest) = match (seed, items.split_first()) {
+        (Some(seed), _) => (seed.clone(), &items[..]),
+        (None, Some((first, rest))) => {
+            step(first);
+            (first.clone(), rest)
+        }
+        (None, None) => {
+            return identity(f).ok_or_else(|| RuntimeError::new(start, RuntimeErrorCode::Length))
+        }
+    };
+    rest.iter().try_fold(first, |acc, item| {
+        let acc = apply_dyad(f, acc, item.clone(), start)?;
+        step(&acc);
+        Ok(acc)
+    })
+}
+
+// apply the monadic f to x n times, calling step with x and every result
+fn times<F>(f: &K, n: i64, x: &K, start: usize, mut step: F) -> Result<K, RuntimeError>
+where
+    F: FnMut(&K),
+{
+    if n < 0 {
+        return Err(RuntimeError::new(start, RuntimeErrorCode::Type));
+    }
+    step(x);
+    (0..n).try_fold(x.clone(), |acc, _| {
+        let acc = f.apply(&[acc], start)?;
+        step(&acc);
+        Ok(acc)
+    })
+}
+
+// apply the monadic f until the result stops changing or comes back to x, calling step with x
+// and every new result
+fn converge<F>(f: &K, x: &K, start: usize, mut step: F) -> Result<K, RuntimeError>
+where
+    F: FnMut(&K),
+{
+    step(x);
+    let mut acc = x.clone();
+    loop {
+        let next = f.apply(&[acc.clone()], start)?;
+        if next.deep_eq(&acc) || next.deep_eq(x) {
+            return Ok(acc);
+        }
+        step(&next);
+        acc = next;
+    }
+}
+
+// f/x folds with a dyadic f and converges with a monadic one, seed f/x folds from the seed and
+// n f/x applies a monadic f n times
+fn iterate<F>(f: &K, args: &[K], start: usize, step: F) -> Result<K, RuntimeError>
+where
+    F: FnMut(&K),
+{
+    match args {
+        [x] if valence(f) == 1 => converge(f, x, start, step),
+        [x] => fold(f, None, x, start, step),
+        [seed, x] => match seed.deref() {
+            K0::Int(n) if valence(f) == 1 => times(f, *n, x, start, step),
+            _ => fold(f, Some(seed), x, start, step),
+        },
+        _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+    }
+}
+
+// f\x: like f/x, but a list of every intermediate result
+fn scan(f: &K, args: &[K], start: usize) -> Result<K, RuntimeError> {
+    match args.last() {
+        Some(x) if valence(f) != 1 && x.list_len() == Some(0) => return Ok(x.clone()),
+        _ => (),
+    }
+    let mut steps = Vec::new();
+    let res = iterate(f, args, start, |k| steps.push(k.clone()))?;
+    // folding an atom gives an atom
+    Ok(if steps.is_empty() { res } else { steps.into() })
+}
+
+// whether a condition holds: a non-zero numeric or char atom
+fn truthy(cond: &K, start: usize) -> Result<bool, RuntimeError> {
+    match cond.deref() {
+        K0::Bool(b) => Ok(*b),
+        K0::Int(i) => Ok(*i != 0),
+        K0::Float(f) => Ok(*f != 0.0),
+        K0::Char(c) => Ok(*c != 0),
+        _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+    }
+}
+
+// verbs and functions, as opposed to data
+fn applicable(k: &K) -> bool {
+    matches!(
+        k.deref(),
+        K0::Verb(_)
+            | K0::Adverb(_)
+            | K0::Builtin(_)
+            | K0::Func(_)
+            | K0::Derived(_, _)
+            | K0::Projection(_, _)
+    )
+}
+
+// .[f;args;h] and @[f;x;h]: the result of applying f, or if that fails h applied to the error name
+// when h is a function and h itself otherwise. Every runtime error is caught.
+fn trap(f: &K, args: &[K], handler: &K, start: usize) -> Result<K, RuntimeError> {
+    match f.apply(args, start) {
+        Ok(res) => Ok(res),
+        Err(e) if applicable(handler) => handler.apply(&[e.code.name().into_bytes().into()], start),
+        Err(_) => Ok(handler.clone()),
+    }
+}
+
+// number of arguments f takes, verbs and builtins are taken to be dyadic
+fn valence(f: &K) -> usize {
+    match f.deref() {
+        K0::Func(func) => func.valence(),
+        K0::Projection(_, args) => args.iter().filter(|a| a.is_none()).count(),
+        _ => 2,
+    }
+}
+
+// by[f;keys;values]: f applied to the values of each group of equal keys, keyed by the keys in the
+// order they first appear
+fn group_by(f: &K, keys: &K, values: &K, start: usize) -> Result<K, RuntimeError> {
+    let items = match (keys.items(), values.list_len()) {
+        (Some(items), Some(len)) if items.len() == len => items,
+        (Some(_), Some(_)) => return Err(RuntimeError::new(start, RuntimeErrorCode::Length)),
+        _ => return Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+    };
+    let mut firsts: Vec<usize> = Vec::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, key) in items.iter().enumerate() {
+        match firsts.iter().position(|&j| items[j].deep_eq(key)) {
+            Some(g) => groups[g].push(i),
+            None => {
+                firsts.push(i);
+                groups.push(vec![i]);
+            }
+        }
+    }
+    let results = groups
+        .iter()
+        .map(|g| f.apply(&[values.select(g)], start))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(K0::Dict(keys.select(&firsts), results.into()).into())
+}
+
+// f'x: apply f to each item of x, or to each value of a dictionary keeping its keys
+fn each(f: &K, x: &K, start: usize) -> Result<K, RuntimeError> {
+    match x.deref() {
+        K0::Dict(keys, values) => keys
+            .dict(&each(f, values, start)?)
+            .map_err(|e| RuntimeError::new(start, e)),
+        _ => match x.items() {
+            Some(items) => items
+                .into_iter()
+                .map(|item| f.apply(&[item], start))
+                .collect::<Result<Vec<_>, _>>()
+                .map(K::from),
+            None => f.apply(std::slice::from_ref(x), start),
+        },
+    }
+}
+
+// f':x: apply f to each item of x and the item before it, the first item going with the seed if
+// there is one or passing through otherwise
+fn each_prior(f: &K, seed: Option<&K>, x: &K, start: usize) -> Result<K, RuntimeError> {
+    let items = match (x.items(), seed) {
+        (Some(items), _) => items,
+        (None, Some(seed)) => return f.apply(&[x.clone(), seed.clone()], start),
+        (None, None) => return Ok(x.clone()),
+    };
+    let mut res = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        res.push(match (i.checked_sub(1), seed) {
+            (Some(prev), _) => f.apply(&[item.clone(), items[prev].clone()], start)?,
+            (None, Some(seed)) => f.apply(&[item.clone(), seed.clone()], start)?,
+            (None, None) => item.clone(),
+        });
+    }
+    Ok(res.into())
+}
+
+// @[x;i;f;y] and @[x;i;f]: x with f applied to its items (or dictionary values) at i, with the
+// matching item of y as the right argument if given. : as f replaces the items with y, and
+// missing dictionary keys are added with a null value.
+fn amend(x: &K, i: &K, f: &K, y: Option<&K>, start: usize) -> Result<K, RuntimeError> {
+    let err = |code| RuntimeError::new(start, code);
+    let (mut keys, mut items, null) = match x.deref() {
+        K0::Dict(keys, values) => (
+            keys.items(),
+            values.items().unwrap_or_default(),
+            values.item(-1),
+        ),
+        _ => (
+            None,
+            x.items().ok_or_else(|| err(RuntimeErrorCode::Type))?,
+            x.item(-1),
+        ),
+    };
+    let indices = i.items().unwrap_or_else(|| vec![i.clone()]);
+    let ys = match (y, i.list_len()) {
+        (Some(y), Some(n)) => match y.items() {
+            Some(ys) if ys.len() == n => ys.into_iter().map(Some).collect(),
+            Some(_) => return Err(err(RuntimeErrorCode::Length)),
+            None => vec![Some(y.clone()); n],
+        },
+        (y, _) => vec![y.cloned(); indices.len()],
+    };
+    for (idx, y) in indices.iter().zip(ys) {
+        let pos = match &mut keys {
+            Some(keys) => match keys.iter().position(|k| k.deep_eq(idx)) {
+                Some(pos) => pos,
+                None => {
+                    keys.push(idx.clone());
+                    items.push(null.clone());
+                    items.len() - 1
+                }
+            },
+            None => match idx.deref() {
+                K0::Int(n) => usize::try_from(*n)
+                    .ok()
+                    .filter(|&n| n < items.len())
+                    .ok_or_else(|| err(RuntimeErrorCode::Index))?,
+                _ => return Err(err(RuntimeErrorCode::Type)),
+            },
+        };
+        items[pos] = match (f.deref(), y) {
+            (K0::Verb(Verb::Colon), Some(y)) => y,
+            (_, Some(y)) => f.apply(&[items[pos].clone(), y], start)?,
+            (_, None) => f.apply(&[items[pos].clone()], start)?,
+        };
+    }
+    match keys {
+        Some(keys) => K::from(keys)
+            .dict(&items.into())
+            .map_err(|e| RuntimeError::new(start, e)),
+        None => Ok(items.into()),
+    }
+}
+
+// x f'y: apply f to pairs of items of x and y, extending atoms
+fn each2(f: &K, x: &K, y: &K, start: usize) -> Result<K, RuntimeError> {
+    let n = match (x.list_len(), y.list_len()) {
+        (None, None) => return f.apply(&[x.clone(), y.clone()], start),
+        (Some(n), Some(m)) if n != m => {
+            return Err(RuntimeError::new(start, RuntimeErrorCode::Length))
+        }
+        (Some(n), _) | (None, Some(n)) => n,
+    };
+    let item = |k: &K, i| match k.list_len() {
+        Some(_) => k.item(i),
+        None => k.clone(),
+    };
+    (0..n as i64)
+        .map(|i| f.apply(&[item(x, i), item(y, i)], start))
+        .collect::<Result<Vec<_>, _>>()
+        .map(K::from)
+}
 
 impl ASTNode {
     pub fn interpret(self) -> Result<K, RuntimeError> {
@@ -14,23 +484,25 @@ impl ASTNode {
             ASTNode::Expr(Spanned(s, _, k)) => match k.deref() {
                 K0::Name(name) => match get_variable(*name) {
                     Some(value) => Ok(value),
-                    None => Err(RuntimeError::new(s, RuntimeErrorCode::UndefinedVariable)),
+                    None => Err(RuntimeError::new(
+                        s,
+                        RuntimeErrorCode::UndefinedVariable(*name),
+                    )),
                 },
                 _ => Ok(k),
             },
-            ASTNode::Apply(Spanned(s, _, (value, args))) => {
+            ASTNode::Apply(Spanned(s, e, (value, args))) => {
                 if let ASTNode::Expr(Spanned(_, _, ref k)) = value.deref() {
                     match (k.deref(), args.len(), args.first()) {
-                        (
-                            K0::Verb(Verb::Dollar),
-                            1,
-                            Some(Some(ASTNode::ExprList(Spanned(_, _, elist)))),
-                        ) if elist.len() > 2 => {
-                            // don't interpret args if the verb is $ (conditional) and args is an exprlist with >2 elements
-                            return Self::conditional(args);
+                        (K0::Verb(Verb::Dollar), n, _) if n > 2 => {
+                            // don't interpret args if the verb is $ (conditional) with >2 args
+                            return Self::conditional(s, args);
+                        }
+                        (K0::Builtin(b @ (Builtin::If | Builtin::While | Builtin::Do)), _, _) => {
+                            return Self::control(*b, s, args);
                         }
                         (
-                            K0::Verb(Verb::Colon),
+                            K0::Verb(Verb::Colon | Verb::ColonColon),
                             2,
                             Some(Some(ASTNode::Expr(Spanned(_, _, name)))),
                         ) if matches!(name.deref(), K0::Name(_)) => {
@@ -45,18 +517,83 @@ impl ASTNode {
                                 }
                             };
                         }
+                        (
+                            K0::Verb(Verb::Colon),
+                            3,
+                            Some(Some(lhs @ ASTNode::Expr(Spanned(_, _, name)))),
+                        ) if matches!(name.deref(), K0::Name(_)) => {
+                            // x op: y assigns x op y, reading x only once
+                            return match (&args[1], &args[2]) {
+                                (Some(op), Some(rhs)) => {
+                                    let rhs = rhs.clone().interpret()?;
+                                    let updated =
+                                        op.clone().apply(&[lhs.clone().interpret()?, rhs])?;
+                                    value.apply(&[name.clone(), updated])
+                                }
+                                _ => {
+                                    Err(RuntimeError::new(s, RuntimeErrorCode::ExpressionExpected))
+                                }
+                            };
+                        }
+                        (
+                            K0::Verb(Verb::Colon),
+                            3,
+                            Some(Some(ASTNode::Apply(Spanned(_, _, (target, index))))),
+                        ) => {
+                            // x[i] op: y amends the items of x at i with op and y
+                            return match (target.deref(), &index[..], &args[1], &args[2]) {
+                                (_, _, Some(_), None) => {
+                                    Err(RuntimeError::new(s, RuntimeErrorCode::ExpressionExpected))
+                                }
+                                (
+                                    lhs @ ASTNode::Expr(Spanned(_, _, name)),
+                                    [Some(i)],
+                                    Some(op),
+                                    Some(rhs),
+                                ) if matches!(name.deref(), K0::Name(_)) => {
+                                    let rhs = rhs.clone().interpret()?;
+                                    let i = i.clone().interpret()?;
+                                    let op = op.clone().interpret()?;
+                                    let updated =
+                                        amend(&lhs.clone().interpret()?, &i, &op, Some(&rhs), s)?;
+                                    value.apply(&[name.clone(), updated])
+                                }
+                                _ => Err(RuntimeError::new(s, RuntimeErrorCode::Nyi)),
+                            };
+                        }
                         _ => (),
                     }
                 }
+                // elided arguments of a verb or function project it over them, like 2+ or f[;1]
+                let elided = args.len() > 1 && args.iter().any(Option::is_none);
                 let mut kargs = VecDeque::with_capacity(args.len());
                 for item in args.into_iter().rev() {
-                    kargs.push_front(match item {
-                        Some(ast) => ast.interpret()?,
-                        None => K0::Nil.into(),
-                    })
+                    kargs.push_front(item.map(ASTNode::interpret).transpose()?)
+                }
+                let start = value.start();
+                let f = value.interpret()?;
+                // a verb failing on its two arguments points at the rest of the expression too
+                let end = match (f.deref(), kargs.len()) {
+                    (K0::Verb(_), 2) => Some(e),
+                    _ => None,
+                };
+                match elided && applicable(&f) {
+                    true => Ok(K0::Projection(f, kargs.into()).into()),
+                    false => apply_owned(
+                        &f,
+                        kargs
+                            .into_iter()
+                            .map(|k| k.unwrap_or_else(|| K0::Nil.into()))
+                            .collect(),
+                        start,
+                    )
+                    .map_err(|err| match err.end {
+                        None if err.location == start => RuntimeError { end, ..err },
+                        _ => err,
+                    }),
                 }
-                value.apply(kargs.make_contiguous())
             }
+            ASTNode::Func(Spanned(_, _, func)) => Ok(K0::Func(func).into()),
             ASTNode::ExprList(Spanned(_, _, mut elist)) => {
                 let last = elist.pop();
                 for ast in elist.into_iter().flatten() {
@@ -70,44 +607,250 @@ impl ASTNode {
         }
     }
 
-    fn conditional(_args: Vec<Option<ASTNode>>) -> Result<K, RuntimeError> {
-        todo!("conditional expression")
+    // $[c;t;c;t;...;f]: only the branch of the first true condition (or the last lone branch) is
+    // evaluated, and nil if there is none
+    fn conditional(start: usize, args: Vec<Option<ASTNode>>) -> Result<K, RuntimeError> {
+        let mut args = args.into_iter();
+        while let Some(cond) = args.next() {
+            let branch = match args.next() {
+                Some(branch) => branch,
+                None => return Self::interpret_arg(cond),
+            };
+            let cond = match cond {
+                Some(cond) => cond,
+                None => {
+                    return Err(RuntimeError::new(
+                        start,
+                        RuntimeErrorCode::ExpressionExpected,
+                    ))
+                }
+            };
+            let s = cond.start();
+            if truthy(&cond.interpret()?, s)? {
+                return Self::interpret_arg(branch);
+            }
+        }
+        Ok(K0::Nil.into())
+    }
+
+    // if[c;e;...], while[c;e;...] and do[n;e;...] evaluate the expressions after the first
+    // argument once if c holds, for as long as c holds or n times, always giving nil
+    fn control(b: Builtin, start: usize, args: Vec<Option<ASTNode>>) -> Result<K, RuntimeError> {
+        let (cond, body) = match args.split_first() {
+            Some((Some(cond), body)) => (cond, body),
+            _ => {
+                return Err(RuntimeError::new(
+                    start,
+                    RuntimeErrorCode::ExpressionExpected,
+                ))
+            }
+        };
+        let run = || -> Result<(), RuntimeError> {
+            for ast in body.iter().flatten() {
+                ast.clone().interpret()?;
+            }
+            Ok(())
+        };
+        let holds = || truthy(&cond.clone().interpret()?, cond.start());
+        match b {
+            Builtin::If => {
+                if holds()? {
+                    run()?;
+                }
+            }
+            Builtin::While => {
+                while holds()? {
+                    run()?;
+                }
+            }
+            _ => match cond.clone().interpret()?.deref() {
+                K0::Int(n) if *n >= 0 => {
+                    for _ in 0..*n {
+                        run()?;
+                    }
+                }
+                _ => return Err(RuntimeError::new(cond.start(), RuntimeErrorCode::Type)),
+            },
+        }
+        Ok(K0::Nil.into())
+    }
+
+    // an elided argument is nil
+    fn interpret_arg(arg: Option<ASTNode>) -> Result<K, RuntimeError> {
+        match arg {
+            Some(ast) => ast.interpret(),
+            None => Ok(K0::Nil.into()),
+        }
     }
 
     fn apply(self, args: &[K]) -> Result<K, RuntimeError> {
         let start = self.start();
-        let k = self.interpret()?;
-        match k.deref() {
+        self.interpret()?.apply(args, start)
+    }
+}
+
+impl K {
+    fn apply(&self, args: &[K], start: usize) -> Result<K, RuntimeError> {
+        self.dispatch(args, start)
+            .map_err(|e| verb_error(self, args.len(), start, e))
+    }
+
+    fn dispatch(&self, args: &[K], start: usize) -> Result<K, RuntimeError> {
+        match self.deref() {
+            // fill the missing arguments in order, calling once there are none left
+            K0::Projection(f, fixed) => {
+                let mut supplied = args.iter();
+                let filled = fixed
+                    .iter()
+                    .map(|a| a.clone().or_else(|| supplied.next().cloned()))
+                    .collect::<Vec<_>>();
+                if supplied.next().is_some() {
+                    return Err(RuntimeError::new(start, RuntimeErrorCode::Rank));
+                }
+                match filled.iter().all(Option::is_some) {
+                    true => f.apply(&filled.into_iter().flatten().collect::<Vec<_>>(), start),
+                    false => Ok(K0::Projection(f.clone(), filled).into()),
+                }
+            }
             K0::Verb(Verb::Plus) => match args.len() {
-                0 => Ok(k),
-                1 => todo!("flip"),
+                0 => Ok(self.clone()),
+                1 => args[0].flip().map_err(|e| RuntimeError::new(start, e)),
                 2 => (&args[0] + &args[1]).map_err(|e| RuntimeError::new(start, e)),
                 _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
             },
             K0::Verb(Verb::Minus) => match args.len() {
-                0 => Ok(k),
+                0 => Ok(self.clone()),
                 1 => (-&args[0]).map_err(|e| RuntimeError::new(start, e)),
                 2 => (&args[0] - &args[1]).map_err(|e| RuntimeError::new(start, e)),
                 _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
             },
             K0::Verb(Verb::Star) => match args.len() {
-                0 => Ok(k),
-                1 => todo!("first"),
+                0 => Ok(self.clone()),
+                1 => Ok(args[0].first()),
                 2 => (&args[0] * &args[1]).map_err(|e| RuntimeError::new(start, e)),
                 _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
             },
             K0::Verb(Verb::Percent) => match args.len() {
-                0 => Ok(k),
-                1 => todo!("first"),
+                0 => Ok(self.clone()),
+                1 => args[0].sqrt().map_err(|e| RuntimeError::new(start, e)),
                 2 => (&args[0] / &args[1]).map_err(|e| RuntimeError::new(start, e)),
                 _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
             },
+            K0::Verb(Verb::And) => match args.len() {
+                0 => Ok(self.clone()),
+                // todo: where
+                1 => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                2 => args[0]
+                    .min(&args[1])
+                    .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(Verb::Caret) => match args.len() {
+                0 => Ok(self.clone()),
+                // todo: null
+                1 => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                2 => args[0]
+                    .power(&args[1])
+                    .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(Verb::Pipe) => match args.len() {
+                0 => Ok(self.clone()),
+                // todo: reverse
+                1 => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                2 => args[0]
+                    .max(&args[1])
+                    .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(v @ (Verb::Lt | Verb::Gt)) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => args[0]
+                    .grade(matches!(v, Verb::Gt))
+                    .map_err(|e| RuntimeError::new(start, e)),
+                2 => match v {
+                    Verb::Lt => args[0].less(&args[1]),
+                    _ => args[0].greater(&args[1]),
+                }
+                .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(Verb::Eq) => match args.len() {
+                0 => Ok(self.clone()),
+                // todo: group
+                1 => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                2 => args[0]
+                    .equal(&args[1])
+                    .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            // `$"..." makes symbols of any bytes as a cast in the interpreter, the tokenizer only
+            // sees an empty symbol and $
+            K0::Verb(Verb::Dollar) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => Ok(args[0].string()),
+                2 => match args[0].deref() {
+                    K0::Sym(s) => args[1].cast(*s).map_err(|e| RuntimeError::new(start, e)),
+                    // todo: n$x pads x to n characters
+                    K0::Int(_) | K0::IntList(_) => {
+                        Err(RuntimeError::new(start, RuntimeErrorCode::Nyi))
+                    }
+                    _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+                },
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(Verb::Question) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => args[0].distinct().map_err(|e| RuntimeError::new(start, e)),
+                2 => args[0]
+                    .find(&args[1])
+                    .map_err(|e| RuntimeError::new(start, e)),
+                // todo: splice
+                3 => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
             K0::Verb(Verb::Comma) => match args.len() {
-                0 => Ok(k),
-                _ => Ok(Vec::from(args).into()), // todo: specialize cases
+                0 => Ok(self.clone()),
+                1 => Ok(Vec::from(args).into()),
+                2 => Ok(args[0].join(&args[1])),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(Verb::Hash) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => Ok(K0::Int(args[0].len() as i64).into()),
+                2 => match args[0].deref() {
+                    K0::Int(n) => args[1].take(*n),
+                    K0::IntList(dims) => args[1].reshape(dims),
+                    _ => Err(RuntimeErrorCode::Type),
+                }
+                .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(Verb::Underscore) => match args.len() {
+                0 => Ok(self.clone()),
+                2 => match args[0].deref() {
+                    K0::Int(n) => args[1].drop(*n),
+                    K0::IntList(i) => args[1].cut(i),
+                    K0::GenList(i) if i.is_empty() => args[1].cut(&[]),
+                    _ => Err(RuntimeErrorCode::Type),
+                }
+                .map_err(|e| RuntimeError::new(start, e)),
+                // todo: floor
+                1 => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(Verb::Tilde) => match args.len() {
+                0 => Ok(self.clone()),
+                // todo: not
+                1 => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                2 => Ok(K0::Int(args[0].deep_eq(&args[1]) as i64).into()),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
             },
             K0::Verb(Verb::Colon) => match args.len() {
-                0 => Ok(k),
+                0 => Ok(self.clone()),
+                2 if READONLY.with(Cell::get) => {
+                    Err(RuntimeError::new(start, RuntimeErrorCode::Restricted))
+                }
                 2 => match args[0].deref() {
                     K0::Name(lhs) => {
                         define_variable(*lhs, &args[1]);
@@ -120,18 +863,55 @@ impl ASTNode {
                 },
                 _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
             },
+            // x::y assigns a global even inside a function
+            K0::Verb(Verb::ColonColon) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => Ok(args[0].clone()),
+                2 if READONLY.with(Cell::get) => {
+                    Err(RuntimeError::new(start, RuntimeErrorCode::Restricted))
+                }
+                2 => match args[0].deref() {
+                    K0::Name(lhs) => {
+                        define_global(*lhs, &args[1]);
+                        Ok(args[1].clone())
+                    }
+                    _ => Err(RuntimeError::new(
+                        start,
+                        RuntimeErrorCode::NameExpectedOnLhs,
+                    )),
+                },
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
             K0::Verb(Verb::Bang) => match args.len() {
-                0 => Ok(k),
+                0 => Ok(self.clone()),
                 1 => match args[0].deref() {
-                    K0::Int(x) => Ok(K0::IntList((0..*x).collect()).into()),
+                    K0::Int(x) if *x >= 0 => Ok(K0::IntList((0..*x).collect()).into()),
+                    K0::Dict(keys, _) => Ok(keys.clone()),
                     _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
                 },
-                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                2 => match args[0].deref() {
+                    K0::Int(x) if *x > 0 => args[0]
+                        .modulo(&args[1])
+                        .map_err(|e| RuntimeError::new(start, e)),
+                    K0::BoolList(_)
+                    | K0::CharList(_)
+                    | K0::IntList(_)
+                    | K0::FloatList(_)
+                    | K0::SymList(_)
+                    | K0::GenList(_) => args[0]
+                        .dict(&args[1])
+                        .map_err(|e| RuntimeError::new(start, e)),
+                    // todo: n!list with a non-positive n rotates the list
+                    K0::Int(_) => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                    _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+                },
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
             },
             K0::Verb(Verb::At) => match args.len() {
-                0 => Ok(k),
+                0 => Ok(self.clone()),
                 1 => Ok(K0::Sym(Sym::new(match args[0].deref() {
                     K0::Nil => b"nil",
+                    K0::Bool(_) => b"b",
                     K0::Char(_) => b"c",
                     K0::Int(_) => b"i",
                     K0::Float(_) => b"f",
@@ -140,17 +920,1647 @@ impl ASTNode {
 
                     K0::Verb(_) => b"v",
                     K0::Adverb(_) => b"a",
+                    K0::Builtin(_) => b"v",
+                    K0::Func(_) => b"v",
+                    K0::Derived(_, _) => b"v",
+                    K0::Projection(_, _) => b"v",
 
+                    K0::BoolList(_) => b"B",
                     K0::CharList(_) => b"C",
                     K0::IntList(_) => b"I",
                     K0::FloatList(_) => b"F",
                     K0::SymList(_) => b"N",
                     K0::GenList(_) => b"l",
+                    K0::Dict(_, _) => b"d",
                 }))
                 .into()),
-                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                2 => match args[0].deref() {
+                    K0::Verb(_) | K0::Builtin(_) => args[0].apply(&args[1..], start),
+                    _ => args[0]
+                        .index_at(&args[1])
+                        .map_err(|e| RuntimeError::new(start, e)),
+                },
+                3 if applicable(&args[0]) => trap(&args[0], &args[1..2], &args[2], start),
+                3 => amend(&args[0], &args[1], &args[2], None, start),
+                4 => amend(&args[0], &args[1], &args[2], Some(&args[3]), start),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Verb(Verb::Dot) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => args[0].value().map_err(|e| RuntimeError::new(start, e)),
+                // index data at depth with the rhs as a path
+                2 if !applicable(&args[0]) => match args[1].items() {
+                    Some(path) => args[0].index_path(&path),
+                    None => args[0].index_path(&[args[1].clone()]),
+                }
+                .map_err(|e| RuntimeError::new(start, e)),
+                // apply the lhs with the items of the rhs as its arguments
+                2 => match args[1].items() {
+                    Some(items) => args[0].apply(&items, start),
+                    None => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+                },
+                3 => match args[1].items() {
+                    Some(items) => trap(&args[0], &items, &args[2], start),
+                    None => trap(&args[0], &args[1..2], &args[2], start),
+                },
+                // todo: .[x;i;f;y] amends at depth
+                4 => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(b @ (Builtin::Asc | Builtin::Desc)) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => args[0]
+                    .sort(matches!(b, Builtin::Desc))
+                    .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(b @ (Builtin::Sum | Builtin::Avg | Builtin::Var | Builtin::Dev)) => {
+                match args.len() {
+                    0 => Ok(self.clone()),
+                    1 => match b {
+                        Builtin::Sum => args[0].sum(),
+                        Builtin::Avg => args[0].avg(),
+                        Builtin::Var => args[0].var(),
+                        _ => args[0].dev(),
+                    }
+                    .map_err(|e| RuntimeError::new(start, e)),
+                    _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+                }
+            }
+            K0::Builtin(
+                b @ (Builtin::Cor | Builtin::Cov | Builtin::Ema | Builtin::Wavg | Builtin::Wsum),
+            ) => match args.len() {
+                0 => Ok(self.clone()),
+                2 => match b {
+                    Builtin::Cor => args[0].cor(&args[1]),
+                    Builtin::Cov => args[0].cov(&args[1]),
+                    Builtin::Ema => args[0].ema(&args[1]),
+                    Builtin::Wavg => args[0].wavg(&args[1]),
+                    _ => args[0].wsum(&args[1]),
+                }
+                .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(Builtin::Config) => match args.len() {
+                0 => Ok(self.clone()),
+                1 if RESTRICTED.with(Cell::get) => {
+                    Err(RuntimeError::new(start, RuntimeErrorCode::Restricted))
+                }
+                1 => match args[0].deref() {
+                    K0::CharList(path) => load_config(Path::new(OsStr::from_bytes(path)))
+                        .map_err(|e| RuntimeError::new(start, e.code)),
+                    _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+                },
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(b @ (Builtin::Bin | Builtin::Within)) => match args.len() {
+                0 => Ok(self.clone()),
+                2 => match b {
+                    Builtin::Bin => args[0].bin(&args[1]),
+                    _ => args[0].within(&args[1]),
+                }
+                .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(Builtin::By) => match args.len() {
+                0 => Ok(self.clone()),
+                3 => group_by(&args[0], &args[1], &args[2], start),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(Builtin::FindAll) => match args.len() {
+                0 => Ok(self.clone()),
+                2 => args[0]
+                    .find_all(&args[1])
+                    .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(Builtin::Raze) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => Ok(args[0].raze()),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            // too few arguments project the function over the rest, like f[;1]
+            K0::Func(f) if (1..f.valence()).contains(&args.len()) => Ok(K0::Projection(
+                self.clone(),
+                args.iter()
+                    .cloned()
+                    .map(Some)
+                    .chain(iter::repeat(None))
+                    .take(f.valence())
+                    .collect(),
+            )
+            .into()),
+            K0::Func(f) => call(f, args, start),
+            // a dictionary applied to keys looks them up, like d@key
+            K0::Dict(_, _) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => self
+                    .index_at(&args[0])
+                    .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
             },
+            // a list applied to indices gives its items there, like x@i
+            K0::BoolList(_)
+            | K0::CharList(_)
+            | K0::IntList(_)
+            | K0::FloatList(_)
+            | K0::SymList(_)
+            | K0::GenList(_) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => self
+                    .index_at(&args[0])
+                    .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Adverb(a) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => Ok(K0::Derived(*a, args[0].clone()).into()),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Derived(Adverb::Quote, f) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => each(f, &args[0], start),
+                2 => each2(f, &args[0], &args[1], start),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Derived(Adverb::QuoteColon, f) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => each_prior(f, None, &args[0], start),
+                2 => each_prior(f, Some(&args[0]), &args[1], start),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            // ratios x is 1%':x, so the first item passes through as a float
+            K0::Builtin(Builtin::Ratios) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => each_prior(
+                    &K0::Verb(Verb::Percent).into(),
+                    Some(&1i64.into()),
+                    &args[0],
+                    start,
+                ),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Derived(Adverb::Slash, f) => match args.len() {
+                0 => Ok(self.clone()),
+                _ => iterate(f, args, start, |_| ()),
+            },
+            K0::Derived(Adverb::Backslash, f) => match args.len() {
+                0 => Ok(self.clone()),
+                _ => scan(f, args, start),
+            },
+            K0::Builtin(Builtin::Enlist) => Ok(Vec::from(args).into()),
+            K0::Builtin(Builtin::QFs) => match args.len() {
+                0 => Ok(self.clone()),
+                2 if RESTRICTED.with(Cell::get) => {
+                    Err(RuntimeError::new(start, RuntimeErrorCode::Restricted))
+                }
+                2 => match args[1].deref() {
+                    K0::CharList(path) => {
+                        read_chunks(Path::new(OsStr::from_bytes(path)), FS_CHUNK_SIZE, |chunk| {
+                            args[0].apply(&[chunk], start)
+                        })
+                        .map(K::from)
+                        .map_err(|e| RuntimeError::new(start, e.code))
+                    }
+                    _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+                },
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(Builtin::QId) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => match args[0].deref() {
+                    K0::Sym(x) => Ok(sanitize_names(&[*x])[0].into()),
+                    K0::SymList(x) => Ok(sanitize_names(x).into()),
+                    _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+                },
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(Builtin::Set) => match args.len() {
+                0 => Ok(self.clone()),
+                2 if READONLY.with(Cell::get) => {
+                    Err(RuntimeError::new(start, RuntimeErrorCode::Restricted))
+                }
+                2 => match args[0].deref() {
+                    K0::Sym(name) => {
+                        define_global(*name, &args[1]);
+                        Ok(args[0].clone())
+                    }
+                    _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+                },
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(b @ (Builtin::Save | Builtin::Load)) => match args.len() {
+                0 => Ok(self.clone()),
+                1 if RESTRICTED.with(Cell::get) => {
+                    Err(RuntimeError::new(start, RuntimeErrorCode::Restricted))
+                }
+                1 => match args[0].deref() {
+                    K0::CharList(path) => {
+                        let path = Path::new(OsStr::from_bytes(path));
+                        match b {
+                            Builtin::Save => save_workspace(path),
+                            _ => load_workspace(path),
+                        }
+                        .map(|names| names.into())
+                        .map_err(|e| RuntimeError::new(start, e))
+                    }
+                    _ => Err(RuntimeError::new(start, RuntimeErrorCode::Type)),
+                },
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(Builtin::Reval) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => match args[0].deref() {
+                    K0::Char(c) => reval(&[*c]),
+                    K0::CharList(src) => reval(src),
+                    _ => Err(RuntimeErrorCode::Type),
+                }
+                .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(b @ (Builtin::Sv | Builtin::Vs)) => match args.len() {
+                0 => Ok(self.clone()),
+                2 => match b {
+                    Builtin::Sv => args[0].sv(&args[1]),
+                    _ => args[0].vs(&args[1]),
+                }
+                .map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            K0::Builtin(Builtin::Get | Builtin::Value) => match args.len() {
+                0 => Ok(self.clone()),
+                1 => args[0].value().map_err(|e| RuntimeError::new(start, e)),
+                _ => Err(RuntimeError::new(start, RuntimeErrorCode::Rank)),
+            },
+            // atoms can't be applied, the primitives left and indexing lists are not there yet
+            x if x.list_len().is_none() && !applicable(self) => {
+                Err(RuntimeError::new(start, RuntimeErrorCode::Type))
+            }
             _ => Err(RuntimeError::new(start, RuntimeErrorCode::Nyi)),
         }
     }
+
+    // value of the global variable named by a symbol, or the values of a dictionary
+    fn value(&self) -> Result<K, RuntimeErrorCode> {
+        match self.deref() {
+            K0::Sym(name) => get_variable(*name).ok_or(RuntimeErrorCode::UndefinedVariable(*name)),
+            K0::Dict(_, values) => Ok(values.clone()),
+            _ => Err(RuntimeErrorCode::Type),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::{load_config, read_chunks};
+    use crate::error::{RuntimeError, RuntimeErrorCode};
+    use crate::k::{K, K0};
+    use crate::parser::Parser;
+    use crate::sym::Sym;
+    use crate::tok::Tokenizer;
+
+    fn eval(src: &[u8]) -> Result<K, RuntimeError> {
+        let tokens = Tokenizer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+        Parser::new(src, tokens)
+            .parse()
+            .unwrap()
+            .unwrap()
+            .interpret()
+    }
+
+    fn eval_str(src: &[u8]) -> String {
+        eval(src).unwrap().to_string()
+    }
+
+    #[test]
+    fn dot_apply_test() {
+        assert_eq!(eval_str(b"+ . 2 3"), "5");
+        assert_eq!(eval_str(b"- . 10 4"), "6");
+        assert_eq!(eval_str(b"- . (1 2 3;1)"), "0 1 2");
+        assert_eq!(eval_str(b"{x*y} . 3 4"), "12");
+        assert_eq!(eval_str(b"{x+y+z} . 1 2 3"), "6");
+        assert_eq!(eval_str(b"(+/) . ,1 2 3"), "6");
+        assert!(matches!(
+            eval(b"+ . 1 2 3").unwrap_err().code,
+            RuntimeErrorCode::Rank
+        ));
+    }
+
+    #[test]
+    fn value_test() {
+        eval(b"valuea:1 2 3").unwrap();
+        assert_eq!(eval_str(b"value`valuea"), "1 2 3");
+        assert_eq!(eval_str(b".`valuea"), "1 2 3");
+        match eval(b"value`valueb").unwrap_err() {
+            RuntimeError {
+                location: 0,
+                code: RuntimeErrorCode::UndefinedVariable(name),
+                ..
+            } => assert_eq!(name, Sym::new(b"valueb")),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn set_get_test() {
+        assert_eq!(eval_str(b"`seta set 4 5 6"), "`seta");
+        assert_eq!(eval_str(b"get`seta"), "4 5 6");
+        assert_eq!(eval_str(b"seta"), "4 5 6");
+        assert_eq!(eval_str(b"set[`setb;`x`y]"), "`setb");
+        assert_eq!(eval_str(b"get`setb"), "`x`y");
+        assert!(matches!(
+            eval(b"get`setc").unwrap_err().code,
+            RuntimeErrorCode::UndefinedVariable(_)
+        ));
+        assert!(matches!(
+            eval(b"1 set 2").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn reval_test() {
+        assert_eq!(eval_str(b"reval \"1+2\""), "3");
+        assert_eq!(eval_str(b"reval \"revala:2*3\""), "6");
+        assert_eq!(eval_str(b"revala"), "6");
+        assert!(matches!(
+            eval(b"reval \"load \\\"/nonexistent\\\"\"")
+                .unwrap_err()
+                .code,
+            RuntimeErrorCode::Restricted
+        ));
+        assert!(matches!(
+            eval(b"reval \"save \\\"/nonexistent/ws\\\"\"")
+                .unwrap_err()
+                .code,
+            RuntimeErrorCode::Restricted
+        ));
+        assert!(matches!(
+            eval(b"reval \"(1\"").unwrap_err().code,
+            RuntimeErrorCode::Parser(_)
+        ));
+        // the restriction is lifted once reval returns
+        assert!(matches!(
+            eval(b"load \"/nonexistent\"").unwrap_err().code,
+            RuntimeErrorCode::Io(_)
+        ));
+    }
+
+    #[test]
+    fn null_literal_test() {
+        for src in &["0N", "0W", "-0W", "0n", "0w", "-0w", "nil"] {
+            assert_eq!(eval_str(src.as_bytes()), *src);
+        }
+        assert_eq!(eval_str(b"1 0N 0W -0W"), "1 0N 0W -0W");
+        assert_eq!(eval_str(b"1.5 0n 0w -0w"), "1.5 0n 0w -0w");
+        assert_eq!(eval_str(b"1 0n"), "1 0n");
+        assert_eq!(eval_str(b"1.5 0N 0W"), "1.5 0n 0w");
+        assert_eq!(eval_str(b"@0N"), "`i");
+        assert_eq!(eval_str(b"@0n"), "`f");
+        assert_eq!(eval_str(b"@nil"), "`nil");
+    }
+
+    #[test]
+    fn empty_list_test() {
+        assert_eq!(eval_str(b"()"), "()");
+        assert_eq!(eval_str(b"#()"), "0");
+        assert_eq!(eval_str(b",()"), ",()");
+        assert_eq!(eval_str(b"#,()"), "1");
+        assert_eq!(eval_str(b"(),()"), "()");
+        assert_eq!(eval_str(b"(),1 2"), "1 2");
+        assert_eq!(eval_str(b"(1;`a),()"), "(1;`a)");
+        assert_eq!(eval_str(b"()~()"), "1");
+        assert_eq!(eval_str(b"()~,()"), "0");
+        assert_eq!(eval_str(b"(1;2)~1 2"), "1");
+    }
+
+    #[test]
+    fn q_id_test() {
+        assert_eq!(eval_str(b".Q.id`a.b`c:d`1x`a.b`get"), "`ab`cd`a1x`ab1`get1");
+        assert_eq!(eval_str(b".Q.id`x:y"), "`xy");
+    }
+
+    #[test]
+    fn min_max_test() {
+        assert_eq!(eval_str(b"2&3"), "2");
+        assert_eq!(eval_str(b"2|3"), "3");
+        assert_eq!(eval_str(b"1 2 3&3 2 1"), "1 2 1");
+        assert_eq!(eval_str(b"1 2 3|3 2 1"), "3 2 3");
+        assert_eq!(eval_str(b"2.5|1 2 3"), "2.5 2.5 3");
+        assert_eq!(eval_str(b"1 2.5&2"), "1 2");
+        assert_eq!(eval_str(b"(1;2.5 3)|2"), "(2;2.5 3)");
+        assert_eq!(eval_str(b"0n&1 2"), "0n 0n");
+        assert_eq!(eval_str(b"0n|1 2"), "1 2");
+        assert!(matches!(
+            eval(b"1 2&1 2 3").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+        assert!(matches!(
+            eval(b"`a|1").unwrap_err().code,
+            RuntimeErrorCode::NonNumeric
+        ));
+    }
+
+    #[test]
+    fn sv_vs_test() {
+        assert_eq!(eval_str(b"\",\"sv(\"a\";\"b\";\"c\")"), "\"a,b,c\"");
+        assert_eq!(eval_str(b"\", \"sv(\"ab\";\"\";\"cd\")"), "\"ab, , cd\"");
+        assert_eq!(eval_str(b"\"\"sv(\"ab\";\"cd\")"), "\"abcd\"");
+        assert_eq!(eval_str(b"\",\"vs\"a,b,c\""), "(,\"a\";,\"b\";,\"c\")");
+        assert_eq!(eval_str(b"\", \"vs\"ab, cd, \""), "(\"ab\";\"cd\";\"\")");
+        assert_eq!(eval_str(b"\",\"vs\"abc\""), ",\"abc\"");
+        assert_eq!(eval_str(b"\",\"sv\",\"vs\"a,b,\""), "\"a,b,\"");
+        assert!(matches!(
+            eval(b"\"\"vs\"abc\"").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+        assert!(matches!(
+            eval(b"\",\"sv 1 2").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn read_chunks_test() {
+        let path = env::temp_dir().join(format!("ak-chunks-test-{}", process::id()));
+        let lines = (0..100).map(|i| i.to_string()).collect::<Vec<_>>();
+        fs::write(&path, lines.join("\r\n")).unwrap();
+
+        let mut seen = Vec::new();
+        let mut chunks = 0;
+        let count = read_chunks(&path, 32, |chunk| {
+            chunks += 1;
+            seen.extend(chunk.items().unwrap().iter().map(|x| x.to_string()));
+            Ok(chunk)
+        })
+        .unwrap();
+        assert_eq!(count, 100);
+        assert!(chunks > 1);
+        let expected = lines.iter().map(|x| match x.len() {
+            1 => format!(",{:?}", x),
+            _ => format!("{:?}", x),
+        });
+        assert!(seen.into_iter().eq(expected));
+
+        let src = format!(".Q.fs[#;{:?}]", path.to_str().unwrap());
+        assert_eq!(eval_str(src.as_bytes()), "100");
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            eval(src.as_bytes()).unwrap_err().code,
+            RuntimeErrorCode::Io(_)
+        ));
+    }
+
+    #[test]
+    fn equal_test() {
+        assert_eq!(eval_str(b"1 2 3=1 0 3"), "101b");
+        assert_eq!(eval_str(b"\"abc\"=\"abd\""), "110b");
+        assert_eq!(eval_str(b"2=2.0"), "1b");
+        assert_eq!(eval_str(b"1 2 3=2.0"), "010b");
+        assert_eq!(eval_str(b"`a`b`c=`b"), "010b");
+        assert_eq!(eval_str(b"(1;`a)=(1.0;`b)"), "10b");
+        assert_eq!(eval_str(b"0n=0n 1"), "10b");
+        assert!(matches!(
+            eval(b"1 2=1 2 3").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+        assert!(matches!(
+            eval(b"\"a\"=97").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn load_config_test() {
+        let path = env::temp_dir().join(format!("ak-config-test-{}", process::id()));
+        fs::write(
+            &path,
+            "/ server settings\nport:8000+80\n\nhosts:`a`b\nname:\"ak\"\nratio:1 2%4\n",
+        )
+        .unwrap();
+        assert_eq!(
+            load_config(&path).unwrap().to_string(),
+            "port | 8080\nhosts| `a`b\nname | \"ak\"\nratio| 0.25 0.5"
+        );
+        let src = format!("config {:?}", path.to_str().unwrap());
+        assert_eq!(
+            eval_str(src.as_bytes()),
+            load_config(&path).unwrap().to_string()
+        );
+        let src = format!("(config {:?})`port", path.to_str().unwrap());
+        assert_eq!(eval_str(src.as_bytes()), "8080");
+
+        // assignments to globals and io are rejected, as are lines that are not assignments
+        for (cfg, code) in [
+            ("a:configb:1\n", RuntimeErrorCode::Restricted),
+            ("a:`configb set 1\n", RuntimeErrorCode::Restricted),
+            ("a:save \"/tmp/ws\"\n", RuntimeErrorCode::Restricted),
+            ("a:1\n1+2\n", RuntimeErrorCode::NameExpectedOnLhs),
+        ] {
+            fs::write(&path, cfg).unwrap();
+            let err = load_config(&path).unwrap_err();
+            assert_eq!(
+                std::mem::discriminant(&err.code),
+                std::mem::discriminant(&code)
+            );
+        }
+        assert!(eval(b"configb").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn grade_test() {
+        assert_eq!(eval_str(b"<3 1 2 1"), "1 3 2 0");
+        assert_eq!(eval_str(b">3 1 2 1"), "0 2 1 3");
+        assert_eq!(eval_str(b"<2.5 0n -1.0"), "1 2 0");
+        assert_eq!(eval_str(b">\"abca\""), "2 1 0 3");
+        assert_eq!(eval_str(b"<`b`a`c`a"), "1 3 0 2");
+        assert_eq!(eval_str(b"<(2;1.5;3)"), "1 0 2");
+        assert_eq!(eval_str(b"#<()"), "0");
+        assert!(matches!(
+            eval(b"<(1;`a)").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn sym_sort_test() {
+        // symbols order by their names, a prefix before its extensions
+        assert!(Sym::new(b"a") < Sym::new(b"ab"));
+        assert!(Sym::new(b"ab") < Sym::new(b"abc"));
+        assert!(Sym::new(b"B") < Sym::new(b"a"));
+        assert_eq!(eval_str(b"asc`abc`a`ab"), "`a`ab`abc");
+        assert_eq!(eval_str(b"desc`abc`a`ab"), "`abc`ab`a");
+        assert_eq!(eval_str(b"<`ab`a`ab`abc`a"), "1 4 0 2 3");
+        assert_eq!(eval_str(b">`ab`a`ab`abc`a"), "3 0 2 1 4");
+        assert_eq!(eval_str(b"asc`b`a`b`a"), "`a`a`b`b");
+        assert_eq!(eval_str(b"desc 3 1 2"), "3 2 1");
+        assert_eq!(eval_str(b"asc(2;1.5)"), "(1.5;2)");
+    }
+
+    #[test]
+    fn deep_index_test() {
+        eval(b"deepm:(1 2 3;4 5 6)").unwrap();
+        assert_eq!(eval_str(b"deepm . 1 2"), "6");
+        assert_eq!(eval_str(b"deepm . 1"), "4 5 6");
+        // past the end of a row, past the last row and deeper than the structure
+        assert_eq!(eval_str(b"deepm . 0 3"), "0N");
+        assert_eq!(eval_str(b"deepm . 2"), "0N 0N 0N");
+        assert_eq!(eval_str(b"deepm . 2 0"), "0N");
+        assert_eq!(eval_str(b"deepm . 0 1 0"), "0N");
+        assert_eq!(eval_str(b"(1.5 2;`a`b) . 1 5"), "`");
+        assert_eq!(eval_str(b"(1.5 2;`a`b) . 0 -1"), "0n");
+        assert_eq!(eval_str(b"\"abc\" . 1 2"), "' '");
+        assert_eq!(eval_str(b"(1 2;3 4) . 1 0"), "3");
+        // a list at some depth selects several items there
+        assert_eq!(eval_str(b"deepm . (0 1;2)"), "3 6");
+        assert_eq!(eval_str(b"deepm . (1;0 2)"), "4 6");
+        assert_eq!(eval_str(b"(deepm . ())~deepm"), "1");
+        assert_eq!(eval_str(b"(`a`b!(1 2;3 4)) . (`b;1)"), "4");
+        assert_eq!(eval_str(b"(`a`b!(1 2;3 4)) . `a"), "1 2");
+        assert!(matches!(
+            eval(b"deepm . `a").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn mod_test() {
+        assert_eq!(eval_str(b"3!10"), "1");
+        assert_eq!(eval_str(b"3!1 2 3 4 5"), "1 2 0 1 2");
+        assert_eq!(eval_str(b"3!-1 -5 0N"), "2 1 0N");
+        assert_eq!(eval_str(b"2!-1.5 3.5"), "0.5 1.5");
+        assert_eq!(eval_str(b"2!(1;3 4)"), "(1;1 0)");
+    }
+
+    #[test]
+    fn raze_test() {
+        assert_eq!(eval_str(b"raze((1;2);(3;4))"), "1 2 3 4");
+        assert_eq!(eval_str(b"raze(1 2;(3;4 5))"), "(1;2;3;4 5)");
+        assert_eq!(
+            eval_str(b"raze((1 2;3 4);,(5 6;7 8))"),
+            "(1 2;3 4;(5 6;7 8))"
+        );
+        assert_eq!(eval_str(b"raze(\"ab\";\"c\")"), "\"abc\"");
+        assert_eq!(eval_str(b"raze 1 2 3"), "1 2 3");
+        assert_eq!(eval_str(b"raze 1"), ",1");
+        for x in [&b"1 2 3"[..], b"(1 2;`a)", b"\"abc\"", b"()"] {
+            let src = [&b"(raze enlist "[..], x, b")~", x].concat();
+            assert_eq!(eval_str(&src), "1");
+        }
+    }
+
+    #[test]
+    fn til_test() {
+        assert_eq!(eval_str(b"!3"), "0 1 2");
+        assert_eq!(eval_str(b"#!0"), "0");
+        assert_eq!(eval_str(b"@!0"), "`I");
+        for src in [&b"!-1"[..], b"!0N", b"!1.5"] {
+            assert!(matches!(
+                eval(src).unwrap_err().code,
+                RuntimeErrorCode::Type
+            ));
+        }
+    }
+
+    #[test]
+    fn find_test() {
+        assert_eq!(eval_str(b"10 20 30 20?20"), "1");
+        assert_eq!(eval_str(b"10 20 30 20?20 40 10"), "1 4 0");
+        assert_eq!(eval_str(b"`a`b`c?`c"), "2");
+        assert_eq!(eval_str(b"(1 2;`a)?`a"), "1");
+        assert_eq!(eval_str(b"(1 2;`a)?,1 2"), ",0");
+        assert_eq!(eval_str(b"10 20 30 20 findall 20"), "1 3");
+        assert_eq!(eval_str(b"(10 20 30 20 findall 20 30 40)~(1 3;,2;!0)"), "1");
+        assert_eq!(eval_str(b"#\"abcab\" findall \"z\""), "0");
+        assert!(matches!(
+            eval(b"1 findall 1").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn dict_test() {
+        assert_eq!(eval_str(b"`a`b`c!1 2 3"), "a| 1\nb| 2\nc| 3");
+        assert_eq!(eval_str(b"1 2!(`x;\"yz\")"), "1| `x\n2| \"yz\"");
+        assert_eq!(eval_str(b"!`a`b`c!1 2 3"), "`a`b`c");
+        assert_eq!(eval_str(b"#`a`b`c!1 2 3"), "3");
+        assert_eq!(eval_str(b"@`a`b!1 2"), "`d");
+        assert_eq!(eval_str(b"(`a`b!1 2)~`a`b!1 2"), "1");
+        assert!(matches!(
+            eval(b"`a`b`c!1 2").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+    }
+
+    #[test]
+    fn char_arith_test() {
+        assert_eq!(eval_str(b"\"a\"+1"), "'b'");
+        assert_eq!(eval_str(b"1+\"a\""), "'b'");
+        assert_eq!(eval_str(b"\"abc\"+1"), "\"bcd\"");
+        assert_eq!(eval_str(b"\"abc\"+0 1 2"), "\"ace\"");
+        assert_eq!(eval_str(b"\"b\"- 1"), "'a'");
+        assert_eq!(eval_str(b"\"z\"+200"), "'B'");
+        assert_eq!(eval_str(b"\"c\"-\"a\""), "2");
+        assert_eq!(eval_str(b"\"abc\"-\"a\""), "0 1 2");
+        assert_eq!(eval_str(b"(\"a\";1)+1"), "('b';2)");
+        for src in [&b"\"a\"+\"b\""[..], b"\"a\"+1.5", b"\"a\"*2"] {
+            assert!(matches!(
+                eval(src).unwrap_err().code,
+                RuntimeErrorCode::Type
+            ));
+        }
+    }
+
+    #[test]
+    fn index_at_test() {
+        assert_eq!(eval_str(b"1 2 3@0 2"), "1 3");
+        assert_eq!(eval_str(b"1 2 3@1"), "2");
+        assert_eq!(eval_str(b"1 2 3@3 -1"), "0N 0N");
+        assert_eq!(eval_str(b"\"abc\"@(0 1;2)"), "(\"ab\";'c')");
+        assert_eq!(eval_str(b"`a`b@5"), "`");
+        assert_eq!(eval_str(b"(1 2;`x)@1 0"), "(`x;1 2)");
+        assert_eq!(eval_str(b"(`a`b!1 2)@`b"), "2");
+        assert_eq!(eval_str(b"(`a`b!1 2)@`b`c`a"), "2 0N 1");
+        assert_eq!(eval_str(b"(`a`b!1.5 2)@`c"), "0n");
+        assert_eq!(eval_str(b"(-)@3"), "-3");
+        assert_eq!(eval_str(b"#1 2 3@!0"), "0");
+        assert!(matches!(
+            eval(b"1 2 3@`a").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn dict_apply_test() {
+        assert_eq!(
+            eval_str(b"dictapply:`a`b`c!10 20 30"),
+            "a| 10\nb| 20\nc| 30"
+        );
+        assert_eq!(eval_str(b"dictapply`b"), "20");
+        assert_eq!(eval_str(b"dictapply `c`a"), "30 10");
+        assert_eq!(eval_str(b"dictapply`d"), "0N");
+        assert_eq!(eval_str(b"dictapply `a`d"), "10 0N");
+        assert_eq!(eval_str(b"dictapply[`c]"), "30");
+        assert_eq!(eval_str(b"(`x`y!(1 2;\"ab\"))`y"), "\"ab\"");
+        assert!(matches!(
+            eval(b"dictapply[`a;`b]").unwrap_err().code,
+            RuntimeErrorCode::Rank
+        ));
+    }
+
+    #[test]
+    fn list_apply_test() {
+        assert_eq!(eval_str(b"listapply:1 2 3; listapply[1]"), "2");
+        assert_eq!(eval_str(b"(1 2 3) 1"), "2");
+        assert_eq!(eval_str(b"\"abc\"[0 2]"), "\"ac\"");
+        assert_eq!(eval_str(b"listapply 5"), "0N");
+        assert_eq!(eval_str(b"(`a`b;1 2)[1]"), "1 2");
+        assert!(matches!(
+            eval(b"listapply[0;1]").unwrap_err().code,
+            RuntimeErrorCode::Rank
+        ));
+    }
+
+    #[test]
+    fn sym_arith_test() {
+        for src in [
+            &b"`a+1"[..],
+            b"1+`a",
+            b"`a`b+1 2",
+            b"1 2*`a`b",
+            b"-`a",
+            b"`a%2",
+            b"(1;`a)+1",
+            b"1.5-`a`b",
+            b"\"a\"+`b",
+            b"`a|1",
+        ] {
+            assert!(matches!(
+                eval(src).unwrap_err().code,
+                RuntimeErrorCode::NonNumeric
+            ));
+        }
+        assert!(matches!(
+            eval(b"(+)+1").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn func_value_test() {
+        assert_eq!(eval_str(b"{x+1}"), "{x+1}");
+        assert_eq!(eval_str(b"{}"), "{}");
+        assert_eq!(eval_str(b"@{x}"), "`v");
+        assert_eq!(eval_str(b"({x};{y})"), "({x};{y})");
+        assert_eq!(eval_str(b"{x+1}~{x+1}"), "1");
+        assert_eq!(eval_str(b"{x+1}~{x+2}"), "0");
+        assert_eq!(eval_str(b"funcf:{x*2}; funcf[21]"), "42");
+        assert_eq!(eval_str(b"funcf"), "{x*2}");
+        assert_eq!(eval_str(b"funcg:funcf; funcg'1 2"), "2 4");
+        assert_eq!(eval_str(b"funcs:({x+1};{x*x}); (funcs@1) 5"), "25");
+        assert_eq!(eval_str(b"{[a;b] a - b }"), "{[a;b] a - b }");
+    }
+
+    #[test]
+    fn over_test() {
+        assert_eq!(eval_str(b"+/1 2 3 4"), "10");
+        assert_eq!(eval_str(b"*/1 2 3 4"), "24");
+        assert_eq!(eval_str(b"|/3 1 4 1 5"), "5");
+        assert_eq!(eval_str(b",/(1 2;3;4 5)"), "1 2 3 4 5");
+        assert_eq!(eval_str(b"+/5"), "5");
+        assert_eq!(eval_str(b"+/"), "Plus/");
+        // empty lists reduce to the identity of the verb
+        assert_eq!(eval_str(b"+/!0"), "0");
+        assert_eq!(eval_str(b"*/!0"), "1");
+        assert_eq!(eval_str(b"&/!0"), "0W");
+        assert_eq!(eval_str(b"|/!0"), "-0W");
+        assert_eq!(eval_str(b",/()"), "()");
+        assert!(matches!(
+            eval(b"-/!0").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+    }
+
+    #[test]
+    fn implicit_args_test() {
+        assert_eq!(eval_str(b"{x*y}[6;7]"), "42");
+        assert_eq!(eval_str(b"{x}[5]"), "5");
+        assert_eq!(eval_str(b"{x+1} 2 3"), "3 4");
+        assert_eq!(eval_str(b"{x,y,z}[1;2;3]"), "1 2 3");
+        assert_eq!(eval_str(b"{x+y}[1;2]"), "3");
+        assert_eq!(eval_str(b"{}[1]"), "nil");
+        assert_eq!(eval_str(b"{{x*2}[x]+y}[3;1]"), "7");
+        assert_eq!(eval_str(b"{x}[1]+{x}[2]"), "3");
+        assert_eq!(eval_str(b"{x+y}/1 2 3"), "6");
+        // two arguments to a function of x, y and z project it, z is still unbound inside
+        assert_eq!(eval_str(b"{x+z}[1;2]"), "{x+z}[1;2;]");
+        match eval(b"{[a;b] a+z}[1;2]").unwrap_err().code {
+            RuntimeErrorCode::UndefinedVariable(name) => assert_eq!(name, Sym::new(b"z")),
+            e => panic!("unexpected error {:?}", e),
+        }
+        assert!(matches!(
+            eval(b"{x}[1;2;3;4]").unwrap_err().code,
+            RuntimeErrorCode::Rank
+        ));
+    }
+
+    #[test]
+    fn trailing_colon_test() {
+        assert!(matches!(
+            eval(b"trailinga:").unwrap_err().code,
+            RuntimeErrorCode::ExpressionExpected
+        ));
+        assert_eq!(eval_str(b":"), "Colon");
+        assert_eq!(eval_str(b"::1 2"), "1 2");
+    }
+
+    #[test]
+    fn explicit_params_test() {
+        assert_eq!(eval_str(b"{[first;second] first-second}[10;3]"), "7");
+        assert_eq!(eval_str(b"{[] 42}[]"), "42");
+        assert_eq!(eval_str(b"{[a] a*2} 4"), "8");
+        assert_eq!(eval_str(b"{[a;b] a+b}"), "{[a;b] a+b}");
+        assert!(matches!(
+            eval(b"{[a;b] x}[1;2]").unwrap_err().code,
+            RuntimeErrorCode::UndefinedVariable(_)
+        ));
+        assert!(matches!(
+            eval(b"{[a] a}[1;2]").unwrap_err().code,
+            RuntimeErrorCode::Rank
+        ));
+    }
+
+    #[test]
+    fn local_scope_test() {
+        assert_eq!(eval_str(b"scopex:5"), "5");
+        assert_eq!(eval_str(b"{scopex:x*2; scopex} 3"), "6");
+        assert_eq!(eval_str(b"scopex"), "5");
+        assert!(matches!(
+            eval(b"{scopelocal:1} 0; scopelocal").unwrap_err().code,
+            RuntimeErrorCode::UndefinedVariable(_)
+        ));
+        // every call gets a fresh frame, so the recursive calls don't clobber r
+        assert_eq!(
+            eval_str(b"scopesum:{[n] r:(({[m] m+scopesum m-1};{[m] 0})@n=0) n; r}; scopesum 4"),
+            "10"
+        );
+        // set always defines a global
+        assert_eq!(eval_str(b"{`scopeset set x} 7; scopeset"), "7");
+    }
+
+    #[test]
+    fn each_test() {
+        assert_eq!(eval_str(b"{x*2}'`a`b!1 2"), "a| 2\nb| 4");
+        assert_eq!(eval_str(b"!{x*2}'`a`b!1 2"), "`a`b");
+        assert_eq!(eval_str(b"{x,x}'`b`a!(1;\"c\")"), "b| 1 1\na| \"cc\"");
+        assert_eq!(eval_str(b"#'(1 2;3;\"abc\")"), "2 1 3");
+        assert_eq!(eval_str(b"{x+1}'5"), "6");
+        // aggregating each column of a column dictionary gives a dictionary of aggregates
+        assert_eq!(eval_str(b"sum'`a`b!(1 2 3;4 5 6)"), "a| 6\nb| 15");
+        assert_eq!(eval_str(b"|/'`a`b!(1 5 3;4 2 6)"), "a| 5\nb| 6");
+        assert_eq!(eval_str(b"1 2,'3 4"), "(1 3;2 4)");
+        assert_eq!(eval_str(b"1+'10 20"), "11 21");
+        assert!(matches!(
+            eval(b"1 2,'3 4 5").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+    }
+
+    #[test]
+    fn dict_order_test() {
+        assert_eq!(eval_str(b"dictorder:`c`a`b!1 2 3"), "c| 1\na| 2\nb| 3");
+        assert_eq!(eval_str(b"!dictorder"), "`c`a`b");
+        assert_eq!(eval_str(b".(`x`y!3 4)"), "3 4");
+        assert_eq!(eval_str(b"value dictorder"), "1 2 3");
+        assert_eq!(eval_str(b"dictorder`a`b`c"), "2 3 1");
+        assert_eq!(eval_str(b"{x*10}'dictorder"), "c| 10\na| 20\nb| 30");
+        assert_eq!(eval_str(b"dictorder,`d`a!4 5"), "c| 1\na| 5\nb| 3\nd| 4");
+        assert_eq!(eval_str(b"!(`b`a!1 2),`c`b!3 4"), "`b`a`c");
+    }
+
+    #[test]
+    fn seeded_over_test() {
+        assert_eq!(eval_str(b"10+/1 2 3 4"), "20");
+        assert_eq!(eval_str(b"10+/!0"), "10");
+        assert_eq!(eval_str(b"10+/5"), "15");
+        assert_eq!(eval_str(b"{x,y}/(1 2;3;4 5)"), "1 2 3 4 5");
+        assert_eq!(eval_str(b"0{x+y*y}/1 2 3"), "14");
+        assert_eq!(eval_str(b"3{x*2}/1"), "8");
+        assert_eq!(eval_str(b"0{x*2}/5"), "5");
+        assert_eq!(eval_str(b"2{[a] a,a}/,1"), "1 1 1 1");
+        assert!(matches!(
+            eval(b"-1{x*2}/1").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn distinct_test() {
+        assert_eq!(eval_str(b"?3 1 3 2 1"), "3 1 2");
+        assert_eq!(eval_str(b"?\"mississippi\""), "\"misp\"");
+        assert_eq!(eval_str(b"?`b`a`b"), "`b`a");
+        assert_eq!(eval_str(b"?(1 2;3;1 2;`a;3)"), "(1 2;3;`a)");
+        assert_eq!(eval_str(b"?1.5 0n 1.5 0n"), "1.5 0n");
+        assert_eq!(eval_str(b"#?0.0 0.0*-1"), "1");
+        assert_eq!(eval_str(b"#?({x};{x};+/;+/)"), "2");
+        assert_eq!(eval_str(b"#?(!100000),!100000"), "100000");
+        assert_eq!(eval_str(b"(?!0)~!0"), "1");
+        assert!(matches!(
+            eval(b"?5").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn scan_test() {
+        assert_eq!(eval_str(b"+\\1 2 3 4"), "1 3 6 10");
+        assert_eq!(eval_str(b"*\\1 2 3 4"), "1 2 6 24");
+        assert_eq!(eval_str(b",\\\"abc\""), "('a';\"ab\";\"abc\")");
+        assert_eq!(eval_str(b"10+\\1 2 3"), "11 13 16");
+        assert_eq!(eval_str(b"+\\5"), "5");
+        assert_eq!(eval_str(b"10+\\5"), "15");
+        assert_eq!(eval_str(b"(+\\!0)~!0"), "1");
+        assert_eq!(eval_str(b"3{x*2}\\1"), "1 2 4 8");
+        assert_eq!(eval_str(b"+\\"), "Plus\\");
+    }
+
+    #[test]
+    fn converge_test() {
+        assert_eq!(eval_str(b"{x|x-1}/5"), "5");
+        assert_eq!(eval_str(b"{0|x-1}/3"), "0");
+        assert_eq!(eval_str(b"{0|x-1}\\3"), "3 2 1 0");
+        // stops when it comes back around to x
+        assert_eq!(eval_str(b"{1-x}\\0"), "0 1");
+        assert_eq!(eval_str(b"{1-x}/0"), "1");
+    }
+
+    #[test]
+    fn conditional_test() {
+        assert_eq!(eval_str(b"$[1;`yes;`no]"), "`yes");
+        assert_eq!(eval_str(b"$[0;`yes;`no]"), "`no");
+        assert_eq!(eval_str(b"$[0;`a;0;`b;`c]"), "`c");
+        assert_eq!(eval_str(b"$[0;`a;2;`b;`c]"), "`b");
+        assert_eq!(eval_str(b"$[1=0;`a;0;`b]"), "nil");
+        assert_eq!(eval_str(b"$[1;$[0;`a;`b];`c]"), "`b");
+        assert_eq!(
+            eval_str(b"{$[x=0;`zero;x=1;`one;`many]}'0 1 2"),
+            "`zero`one`many"
+        );
+        // only the taken branch is evaluated
+        assert_eq!(eval_str(b"$[1;condtaken:1;condnot:1]; condtaken"), "1");
+        assert!(matches!(
+            eval(b"condnot").unwrap_err().code,
+            RuntimeErrorCode::UndefinedVariable(_)
+        ));
+        assert!(matches!(
+            eval(b"$[`a;1;2]").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn stats_test() {
+        assert_eq!(eval_str(b"sum 1 2 3 4"), "10");
+        assert_eq!(eval_str(b"sum 1 0N 3"), "4");
+        assert_eq!(eval_str(b"sum 1.5 0n 2"), "3.5");
+        assert_eq!(eval_str(b"sum !0"), "0");
+        assert_eq!(eval_str(b"avg 1 2 0N 3"), "2");
+        assert_eq!(eval_str(b"avg 1.5 2.5"), "2");
+        assert_eq!(eval_str(b"var 2 4 4 4 5 5 7 9"), "4");
+        assert_eq!(eval_str(b"dev 2 4 4 4 5 5 7 9"), "2");
+        assert_eq!(eval_str(b"var 1 0n 3"), "1");
+        assert_eq!(eval_str(b"dev 1 0N 3"), "1");
+        assert_eq!(eval_str(b"var 5"), "0");
+        for src in [&b"avg !0"[..], b"var 0n 0n", b"dev 0N 0N", b"avg 0n"] {
+            assert_eq!(eval_str(src), "0n");
+        }
+        assert!(matches!(
+            eval(b"avg `a`b").unwrap_err().code,
+            RuntimeErrorCode::NonNumeric
+        ));
+        assert!(matches!(
+            eval(b"var \"ab\"").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn control_test() {
+        assert_eq!(
+            eval_str(b"ctli:0; ctlacc:0; while[5-ctli; ctlacc:ctlacc+ctli; ctli:ctli+1]"),
+            "nil"
+        );
+        assert_eq!(eval_str(b"ctlacc"), "10");
+        assert_eq!(eval_str(b"ctli"), "5");
+        assert_eq!(eval_str(b"ctldo:1; do[4; ctldo:ctldo*2]; ctldo"), "16");
+        assert_eq!(
+            eval_str(b"ctlif:0; if[1; ctlif:1; ctlif:ctlif+1]; ctlif"),
+            "2"
+        );
+        assert_eq!(eval_str(b"ctlelse:0; if[0; ctlelse:1]; ctlelse"), "0");
+        // the condition is checked before every iteration
+        assert_eq!(
+            eval_str(b"ctlnone:0; while[0; ctlnone:1]; do[0; ctlnone:2]; ctlnone"),
+            "0"
+        );
+        assert_eq!(eval_str(b"{[n] r:1; do[n; r:r*2]; r} 10"), "1024");
+        assert!(matches!(
+            eval(b"do[-1; 1]").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+        assert!(matches!(
+            eval(b"if[`a; 1]").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn weighted_test() {
+        assert_eq!(eval_str(b"1 2 3 wsum 4 5 6"), "32");
+        assert_eq!(eval_str(b"1 3 wavg 10 20"), "17.5");
+        assert_eq!(eval_str(b"2 wavg 1 2 3"), "2");
+        assert_eq!(eval_str(b"1 2 3 wsum 2"), "12");
+        // pairs with a null are left out
+        assert_eq!(eval_str(b"1 0N 3 wavg 10 20 30"), "25");
+        assert_eq!(eval_str(b"0 0 wavg 1 2"), "0n");
+        assert!(matches!(
+            eval(b"1 2 wavg 1 2 3").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+        assert!(matches!(
+            eval(b"1 2 wsum `a`b").unwrap_err().code,
+            RuntimeErrorCode::NonNumeric
+        ));
+    }
+
+    #[test]
+    fn global_assign_test() {
+        assert_eq!(
+            eval_str(b"gcount:0; gincr:{gcount::gcount+1}; gincr[]; gincr[]; gcount"),
+            "2"
+        );
+        // a plain : still makes a local, which :: then reads from
+        assert_eq!(
+            eval_str(b"glocal:1; {glocal:10; gsum::glocal+1} 0; glocal"),
+            "1"
+        );
+        assert_eq!(eval_str(b"gsum"), "11");
+        assert_eq!(eval_str(b"gtop::3"), "3");
+        assert_eq!(eval_str(b"gtop"), "3");
+        assert!(matches!(
+            eval(b"\"a\"::2").unwrap_err().code,
+            RuntimeErrorCode::NameExpectedOnLhs
+        ));
+    }
+
+    #[test]
+    fn cor_cov_test() {
+        assert_eq!(eval_str(b"1 2 3 4 cov 2 4 6 8"), "2.5");
+        assert_eq!(eval_str(b"1 2 3 4 cor 2 4 6 8"), "1");
+        assert_eq!(eval_str(b"1 2 3 cor 3 2 1"), "-1");
+        assert_eq!(eval_str(b"1 2 3 cov 1 0n 3"), "1");
+        // nothing to correlate if either side is constant
+        assert_eq!(eval_str(b"1 2 3 cor 5 5 5"), "0n");
+        assert_eq!(eval_str(b"1 2 3 cov 5 5 5"), "0");
+        assert!(matches!(
+            eval(b"1 2 3 cor 1 2").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+    }
+
+    #[test]
+    fn modified_assign_test() {
+        assert_eq!(eval_str(b"modx:5; modx+:3"), "8");
+        assert_eq!(eval_str(b"modx"), "8");
+        assert_eq!(eval_str(b"modl:1 2; modl,:3; modl*:2; modl"), "2 4 6");
+        assert_eq!(eval_str(b"modf:0; {modf::10; modf-:1; modf} 0"), "9");
+        assert_eq!(eval_str(b"modf"), "10");
+        assert!(matches!(
+            eval(b"modundefined+:1").unwrap_err().code,
+            RuntimeErrorCode::UndefinedVariable(_)
+        ));
+        assert!(matches!(
+            eval(b"modx+:").unwrap_err().code,
+            RuntimeErrorCode::ExpressionExpected
+        ));
+        // an indexed name is amended at the index
+        assert_eq!(eval_str(b"modi:1 2 3; modi[0]+:10; modi"), "11 2 3");
+        assert_eq!(eval_str(b"modi[1 2]*:10 100; modi"), "11 20 300");
+        assert_eq!(eval_str(b"modd:`a`b!1 2; modd[`a]+:5; modd"), "a| 6\nb| 2");
+        assert!(matches!(
+            eval(b"modi[5]+:1").unwrap_err().code,
+            RuntimeErrorCode::Index
+        ));
+        assert!(matches!(
+            eval(b"modi[0;1]+:1").unwrap_err().code,
+            RuntimeErrorCode::Nyi
+        ));
+        // anywhere else a verb-colon pair is the monadic verb
+        assert_eq!(eval_str(b"-:3"), "-3");
+        assert_eq!(eval_str(b"#:'(1 2;3)"), "2 1");
+        assert_eq!(eval_str(b"@[1 2 3;0;-:]"), "-1 2 3");
+        assert_eq!(eval_str(b"modc:#:; modc 1 2 3"), "3");
+    }
+
+    #[test]
+    fn each_prior_test() {
+        assert_eq!(eval_str(b"-':1 4 9 16"), "1 3 5 7");
+        assert_eq!(eval_str(b"0-':1 4 9 16"), "1 3 5 7");
+        assert_eq!(eval_str(b",':1 2 3"), "(1;2 1;3 2)");
+        assert_eq!(eval_str(b"ratios 2 4 8"), "2 2 2");
+        assert_eq!(eval_str(b"ratios 1.5 3 0.75"), "1.5 2 0.25");
+        // a zero before an item divides by zero
+        assert_eq!(eval_str(b"ratios 1 0 2"), "1 0 0w");
+        assert_eq!(eval_str(b"ratios 4"), "4");
+    }
+
+    #[test]
+    fn first_flip_test() {
+        assert_eq!(eval_str(b"*1 2 3"), "1");
+        assert_eq!(eval_str(b"*\"abc\""), "'a'");
+        assert_eq!(eval_str(b"*(1 2;3)"), "1 2");
+        assert_eq!(eval_str(b"*5"), "5");
+        assert_eq!(eval_str(b"*!0"), "0N");
+        assert_eq!(eval_str(b"*`b`a!1 2"), "1");
+        assert_eq!(eval_str(b"+(1 2 3;4 5 6)"), "(1 4;2 5;3 6)");
+        assert_eq!(eval_str(b"++(1 2 3;4 5 6)"), "(1 2 3;4 5 6)");
+        assert_eq!(eval_str(b"+(1 2;0)"), "(1 0;2 0)");
+        assert_eq!(eval_str(b"+1 2 3"), "1 2 3");
+        assert_eq!(eval_str(b"+5"), "5");
+        assert!(matches!(
+            eval(b"+(1 2;3 4 5)").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+    }
+
+    #[test]
+    fn ema_test() {
+        assert_eq!(eval_str(b"0.5 ema 2 4 8 0"), "2 3 5.5 2.75");
+        assert_eq!(eval_str(b"0.25 ema 4 8"), "4 5");
+        assert_eq!(eval_str(b"0.5 ema 3"), "3");
+        for src in [
+            &b"0 ema 1 2"[..],
+            b"1 ema 1 2",
+            b"1.5 ema 1 2",
+            b"-0.5 ema 1 2",
+            b"0n ema 1 2",
+        ] {
+            assert!(matches!(
+                eval(src).unwrap_err().code,
+                RuntimeErrorCode::Domain
+            ));
+        }
+        assert!(matches!(
+            eval(b"0.5 ema `a`b").unwrap_err().code,
+            RuntimeErrorCode::NonNumeric
+        ));
+    }
+
+    #[test]
+    fn sqrt_test() {
+        assert_eq!(eval_str(b"%16"), "4");
+        assert_eq!(eval_str(b"%1 4 9"), "1 2 3");
+        assert_eq!(eval_str(b"@%16"), "`f");
+        assert_eq!(eval_str(b"@%1 4 9"), "`F");
+        assert_eq!(eval_str(b"%2.25 0"), "1.5 0");
+        assert_eq!(eval_str(b"%-1"), "0n");
+        assert_eq!(eval_str(b"%0N 4"), "0n 2");
+        assert_eq!(eval_str(b"%(4;9 16)"), "(2;3 4)");
+        assert!(matches!(
+            eval(b"%`a").unwrap_err().code,
+            RuntimeErrorCode::NonNumeric
+        ));
+    }
+
+    #[test]
+    fn within_bin_test() {
+        assert_eq!(eval_str(b"1 5 10 15 within 5 10"), "0110b");
+        assert_eq!(eval_str(b"7 within 5 10"), "1b");
+        assert_eq!(eval_str(b"1.5 2.5 within (1;2)"), "10b");
+        assert_eq!(eval_str(b"\"acz\" within \"az\""), "111b");
+        assert_eq!(eval_str(b"0 10 20 bin 5 10 25 -1"), "0 1 2 -1");
+        assert_eq!(eval_str(b"0 10 20 bin 15"), "1");
+        assert_eq!(eval_str(b"1.5 2.5 bin 2"), "0");
+        assert_eq!(eval_str(b"`a`c`e bin `d"), "1");
+        assert!(matches!(
+            eval(b"1 2 within 1 2 3").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+        assert!(matches!(
+            eval(b"1 2 bin `a").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn amend_test() {
+        assert_eq!(eval_str(b"@[1 2 3 4;1 3;+;10]"), "1 12 3 14");
+        assert_eq!(eval_str(b"@[1 2 3 4;1 3;+;10 20]"), "1 12 3 24");
+        assert_eq!(eval_str(b"@[1 2 3;0;-]"), "-1 2 3");
+        assert_eq!(eval_str(b"@[1 2 3;1;:;`a]"), "(1;`a;3)");
+        // repeated indices are amended in turn
+        assert_eq!(eval_str(b"@[0 0;1 1;+;1]"), "0 2");
+        assert_eq!(eval_str(b"@[(1 2;3);0;{x,x}]"), "(1 2 1 2;3)");
+        assert_eq!(eval_str(b"@[`a`b!1 2;`b;*;10]"), "a| 1\nb| 20");
+        assert_eq!(eval_str(b"@[`a`b!1 2;`c;:;3]"), "a| 1\nb| 2\nc| 3");
+        assert_eq!(eval_str(b"amendx:1 2 3; @[amendx;0;:;9]; amendx"), "1 2 3");
+        for src in [&b"@[1 2 3;3;+;1]"[..], b"@[1 2 3;-1;-]"] {
+            assert!(matches!(
+                eval(src).unwrap_err().code,
+                RuntimeErrorCode::Index
+            ));
+        }
+        assert!(matches!(
+            eval(b"@[1 2 3;0 1;+;1 2 3]").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+    }
+
+    #[test]
+    fn trap_test() {
+        assert_eq!(eval_str(b".[{x+`a};1;`err]"), "`err");
+        assert_eq!(eval_str(b".[+;1 2;`err]"), "3");
+        assert_eq!(eval_str(b".[+;(1;`a);{x}]"), "\"NonNumeric\"");
+        assert_eq!(eval_str(b".[{[a] a};1 2;`rank]"), "`rank");
+        assert_eq!(eval_str(b"@[{x+1};1;`err]"), "2");
+        assert_eq!(eval_str(b"@[{x+1};`a;`err]"), "`err");
+        assert_eq!(eval_str(b"@[-;`a;{`caught}]"), "`caught");
+        assert_eq!(
+            eval_str(b"@[{undefinedname};1;{x}]"),
+            "\"UndefinedVariable: undefinedname\""
+        );
+        assert_eq!(eval_str(b".[{x};1 2;{x}]"), "\"Rank\"");
+        // globals set before the error stay set, and the locals of the failed call are gone
+        assert_eq!(
+            eval_str(b"trapg:1; .[{traplocal:5; trapg::2; x+`a};1;0]; trapg"),
+            "2"
+        );
+        assert!(matches!(
+            eval(b"traplocal").unwrap_err().code,
+            RuntimeErrorCode::UndefinedVariable(_)
+        ));
+        assert_eq!(eval_str(b"{x+trapg} 1"), "3");
+    }
+
+    #[test]
+    fn bool_test() {
+        for src in ["1b", "0b", "101b", ",1b", "(1b;2)"] {
+            assert_eq!(eval_str(src.as_bytes()), src);
+        }
+        assert_eq!(eval_str(b"(1b;0b)"), "10b");
+        assert_eq!(eval_str(b"@1b"), "`b");
+        assert_eq!(eval_str(b"@10b"), "`B");
+        assert_eq!(eval_str(b"1b+1b"), "2");
+        assert_eq!(eval_str(b"101b*3"), "3 0 3");
+        assert_eq!(eval_str(b"-(10b)"), "-1 0");
+        assert_eq!(eval_str(b"1.5+01b"), "1.5 2.5");
+        assert_eq!(eval_str(b"sum 1101b"), "3");
+        assert_eq!(eval_str(b"1 2 3<2"), "100b");
+        assert_eq!(eval_str(b"1 2 3>2"), "001b");
+        assert_eq!(eval_str(b"`a`c>`b"), "01b");
+        assert_eq!(eval_str(b"1b=1"), "1b");
+        assert_eq!(eval_str(b"0110b&1010b"), "0010b");
+        assert_eq!(eval_str(b"0110b|1010b"), "1110b");
+        assert_eq!(eval_str(b"01b|1"), "1 1");
+        assert_eq!(eval_str(b"`a`b`c@101b"), "`b`a`b");
+        assert_eq!(eval_str(b"$[1>2;`y;`n]"), "`n");
+        let k = eval(b"1001b").unwrap();
+        let mut buf = vec![];
+        k.write_to(&mut buf);
+        assert!(K::read_from(&mut &buf[..]).unwrap().deep_eq(&k));
+    }
+
+    #[test]
+    fn sym_cast_test() {
+        assert_eq!(eval_str(b"`$\"hello world\""), "`hello world");
+        assert!(eval(b"`$\"hello world\"")
+            .unwrap()
+            .deep_eq(&K0::Sym(Sym::new(b"hello world"))));
+        assert_eq!(eval_str(b"`$\"a\""), "`a");
+        assert_eq!(eval_str(b"`$(\"a-b\";\"c d\")"), "`a-b`c d");
+        assert_eq!(eval_str(b"(`$\"x y\")=`$\"x y\""), "1b");
+        assert!(matches!(
+            eval(b"`$1").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+        // longer than a shard of the symbol table holds
+        assert!(matches!(
+            eval(b"`$67108865#\"a\"").unwrap_err().code,
+            RuntimeErrorCode::SymbolTableFull
+        ));
+    }
+
+    #[test]
+    fn cast_test() {
+        assert_eq!(eval_str(b"`i$3.7"), "3");
+        assert_eq!(eval_str(b"`i$-3.7 0n 0w"), "-3 0N 0W");
+        assert_eq!(eval_str(b"`int$\"AB\""), "65 66");
+        assert_eq!(eval_str(b"`i$101b"), "1 0 1");
+        assert_eq!(eval_str(b"`f$3"), "3");
+        assert_eq!(eval_str(b"@`f$3"), "`f");
+        assert_eq!(eval_str(b"`f$1 0N"), "1 0n");
+        assert_eq!(eval_str(b"`c$65"), "'A'");
+        assert_eq!(eval_str(b"`c$72 73"), "\"HI\"");
+        assert_eq!(eval_str(b"`b$0 2 0"), "010b");
+        assert_eq!(eval_str(b"`$\"abc\""), "`abc");
+        assert_eq!(eval_str(b"`$`abc"), "\"abc\"");
+        assert_eq!(eval_str(b"`$`ab`c"), "(\"ab\";,\"c\")");
+        assert_eq!(eval_str(b"`i$(1.5;2 3.5)"), "(1;2 3)");
+        for src in [&b"`i$`sym"[..], b"`c$1.5", b"`f$(1;`b)", b"`$1"] {
+            assert!(matches!(
+                eval(src).unwrap_err().code,
+                RuntimeErrorCode::Type
+            ));
+        }
+        assert!(matches!(
+            eval(b"`date$1").unwrap_err().code,
+            RuntimeErrorCode::Domain
+        ));
+    }
+
+    #[test]
+    fn byte_display_test() {
+        // bytes that aren't readable text print as hex, and everything prints as it reads
+        for src in [
+            &b"0xdeadbeef"[..],
+            b"0xff",
+            b"0x0041",
+            b"\"a\\tb\\n\"",
+            b"\"say \\\"hi\\\"\"",
+            "\"caf\u{e9}\"".as_bytes(),
+        ] {
+            assert_eq!(eval_str(src), String::from_utf8_lossy(src));
+            assert_eq!(
+                eval_str(eval_str(src).as_bytes()),
+                String::from_utf8_lossy(src)
+            );
+        }
+        assert_eq!(eval_str(b"0x4142"), "\"AB\"");
+    }
+
+    #[test]
+    fn string_test() {
+        assert_eq!(eval_str(b"$42"), "\"42\"");
+        assert_eq!(eval_str(b"$`abc"), "\"abc\"");
+        assert_eq!(eval_str(b"$1 2 3"), "(,\"1\";,\"2\";,\"3\")");
+        assert_eq!(eval_str(b"$-12 0N"), "(\"-12\";\"0N\")");
+        assert_eq!(
+            eval_str(b"$0n 0w -0w 1.5"),
+            "(\"0n\";\"0w\";\"-0w\";\"1.5\")"
+        );
+        assert_eq!(eval_str(b"$\"ab\""), "(,\"a\";,\"b\")");
+        assert_eq!(eval_str(b"$10b"), "(,\"1\";,\"0\")");
+        assert_eq!(eval_str(b"$(1;`a`bc)"), "(,\"1\";(,\"a\";\"bc\"))");
+        assert_eq!(eval_str(b"$`a`b!1 2"), "a| ,\"1\"\nb| ,\"2\"");
+        // the same spelling as display
+        for src in ["0n", "-0w", "0W", "2.5", "-7"] {
+            let shown = eval_str(src.as_bytes());
+            assert_eq!(
+                eval_str(format!("${}", src).as_bytes()),
+                format!("{:?}", shown)
+            );
+        }
+    }
+
+    #[test]
+    fn power_test() {
+        assert_eq!(eval_str(b"2^10"), "1024");
+        assert_eq!(eval_str(b"@2^10"), "`f");
+        assert_eq!(eval_str(b"1 2 3^2"), "1 4 9");
+        assert_eq!(eval_str(b"@1 2 3^2"), "`F");
+        assert_eq!(eval_str(b"4^0.5 -1"), "2 0.25");
+        assert_eq!(eval_str(b"2 3^(1;2 3)"), "(2;9 27)");
+        assert_eq!(eval_str(b"0N^2"), "0n");
+        assert_eq!(eval_str(b"10b^2"), "1 0");
+        assert!(matches!(
+            eval(b"1 2^1 2 3").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+        assert!(matches!(
+            eval(b"\"a\"^2").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+        assert!(matches!(
+            eval(b"`a^2").unwrap_err().code,
+            RuntimeErrorCode::NonNumeric
+        ));
+    }
+
+    #[test]
+    fn drop_cut_test() {
+        assert_eq!(eval_str(b"2_1 2 3 4"), "3 4");
+        assert_eq!(eval_str(b"-2_1 2 3 4"), "1 2");
+        assert_eq!(eval_str(b"1_\"abc\""), "\"bc\"");
+        assert_eq!(eval_str(b"(5_1 2 3)~!0"), "1");
+        assert_eq!(eval_str(b"1_`a`b!1 2"), "b| 2");
+        assert_eq!(eval_str(b"2 4_\"abcdef\""), "(\"cd\";\"ef\")");
+        assert_eq!(eval_str(b"0 1 3_1 2 3 4"), "(,1;2 3;,4)");
+        assert_eq!(eval_str(b"1 1 3_\"abc\""), "(\"\";\"bc\";\"\")");
+        assert_eq!(eval_str(b"(!0)_1 2"), ",1 2");
+        for src in [&b"2 1_\"abc\""[..], b"1 4_\"abc\"", b"-1 1_\"abc\""] {
+            assert!(matches!(
+                eval(src).unwrap_err().code,
+                RuntimeErrorCode::Index
+            ));
+        }
+        assert!(matches!(
+            eval(b"1_2").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn take_reshape_test() {
+        assert_eq!(eval_str(b"2#1 2 3"), "1 2");
+        assert_eq!(eval_str(b"5#1 2 3"), "1 2 3 1 2");
+        assert_eq!(eval_str(b"-2#1 2 3"), "2 3");
+        assert_eq!(eval_str(b"-5#1 2 3"), "2 3 1 2 3");
+        assert_eq!(eval_str(b"3#7"), "7 7 7");
+        assert_eq!(eval_str(b"3#\"ab\""), "\"aba\"");
+        assert_eq!(eval_str(b"2#!0"), "0N 0N");
+        assert_eq!(eval_str(b"1#`a`b!1 2"), "a| 1");
+        assert_eq!(eval_str(b"2 3#!6"), "(0 1 2;3 4 5)");
+        assert_eq!(eval_str(b"3 2#1 2"), "(1 2;1 2;1 2)");
+        assert_eq!(eval_str(b"2 2 2#!8"), "((0 1;2 3);(4 5;6 7))");
+        assert_eq!(eval_str(b"0N 2#!6"), "(0 1;2 3;4 5)");
+        assert_eq!(eval_str(b"3 0N#!6"), "(0 1;2 3;4 5)");
+        assert_eq!(eval_str(b"2 3#\"abcdef\""), "(\"abc\";\"def\")");
+        for src in [&b"2 -3#!6"[..], b"0N 4#!6"] {
+            assert!(matches!(
+                eval(src).unwrap_err().code,
+                RuntimeErrorCode::Length
+            ));
+        }
+        assert!(matches!(
+            eval(b"`a#1 2").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
+
+    #[test]
+    fn bracket_apply_test() {
+        assert_eq!(eval_str(b"+[2;3]"), "5");
+        assert_eq!(eval_str(b"{x+y}[2;3]"), "5");
+        assert_eq!(eval_str(b"(-)[5;2]"), "3");
+        // brackets bind tighter than the verb on their left
+        assert_eq!(eval_str(b"5*[1;2]"), "10");
+        assert_eq!(eval_str(b"-*[2;4]"), "-8");
+        assert_eq!(eval_str(b"bracketf:{x*y}; bracketf[3;4]"), "12");
+        assert_eq!(eval_str(b"{x,y}[1;{x*10}[2]]"), "1 20");
+        assert_eq!(eval_str(b"{[a;b;c] a+b*c}[1;2;3]"), "7");
+        // elided arguments project the function
+        assert_eq!(eval_str(b"{@y}[1;]"), "{@y}[1;]");
+        assert_eq!(eval_str(b"{@x}[]"), "`nil");
+        assert!(matches!(
+            eval(b"{[a] a}[1;2]").unwrap_err().code,
+            RuntimeErrorCode::Rank
+        ));
+    }
+
+    #[test]
+    fn projection_test() {
+        assert_eq!(eval_str(b"projf:2+; projf 3"), "5");
+        assert_eq!(eval_str(b"projg:+[;10]; projg 5"), "15");
+        assert_eq!(eval_str(b"projg"), "Plus[;10]");
+        assert_eq!(eval_str(b"@projg"), "`v");
+        assert_eq!(eval_str(b"{x-y}[;1] 5"), "4");
+        assert_eq!(eval_str(b"{x,y,z}[1;;3] 2"), "1 2 3");
+        assert_eq!(eval_str(b"{x,y,z}[;2;][1] 3"), "1 2 3");
+        assert_eq!(eval_str(b"{x,y,z}[;2;][1]"), "{x,y,z}[1;2;]");
+        assert_eq!(eval_str(b"(2*)'1 2 3"), "2 4 6");
+        assert_eq!(eval_str(b"{x+y}[;1]/[3;0]"), "3");
+        // fewer arguments than the valence project too
+        assert_eq!(eval_str(b"{x+y}[1]"), "{x+y}[1;]");
+        assert_eq!(eval_str(b"projh:{x+y}[1]; projh 5"), "6");
+        assert_eq!(eval_str(b"{x+y+z}[1;2][3]"), "6");
+        assert_eq!(eval_str(b"{[a;b] a-b}[5] 1"), "4");
+        assert_eq!(eval_str(b"{x+y}[1]'10 20"), "11 21");
+        assert!(matches!(
+            eval(b"(2+)[1;2]").unwrap_err().code,
+            RuntimeErrorCode::Rank
+        ));
+        let k = eval(b"{x*y}[;3]").unwrap();
+        let mut buf = vec![];
+        k.write_to(&mut buf);
+        assert!(K::read_from(&mut &buf[..]).unwrap().deep_eq(&k));
+    }
+
+    #[test]
+    fn right_to_left_test() {
+        // no precedence: each verb takes everything on its right as its right argument
+        assert_eq!(eval_str(b"2*3+4"), "14");
+        assert_eq!(eval_str(b"1+2*3"), "7");
+        assert_eq!(eval_str(b"-3-2-1"), "-4");
+        assert_eq!(eval_str(b"10-2-3"), "11");
+        assert_eq!(eval_str(b"100%10%2"), "20");
+        assert_eq!(eval_str(b"2^3^2"), "512");
+        assert_eq!(eval_str(b"1,2,3*2"), "1 2 6");
+        assert_eq!(eval_str(b"#1 2 3,4"), "4");
+        assert_eq!(eval_str(b"{x*2}1+2"), "6");
+        assert_eq!(eval_str(b"(2*3)+4"), "10");
+        assert_eq!(eval_str(b"rtlx:2; rtlx+3*rtlx"), "8");
+    }
+
+    #[test]
+    fn overflow_test() {
+        // ints saturate at 0W and -0W
+        assert_eq!(eval_str(b"0W+1"), "0W");
+        assert_eq!(eval_str(b"-0W-5"), "-0W");
+        assert_eq!(eval_str(b"0W-(-5)"), "0W");
+        assert_eq!(eval_str(b"-1+-0W"), "-0W");
+        assert_eq!(eval_str(b"3037000500*3037000500"), "0W");
+        assert_eq!(eval_str(b"-3037000500*3037000500"), "-0W");
+        assert_eq!(
+            eval_str(b"4611686018427387904*1 -2 2"),
+            "4611686018427387904 -0W 0W"
+        );
+        assert_eq!(eval_str(b"1 2 3+0W"), "0W 0W 0W");
+        assert_eq!(eval_str(b"0W 5-(-1;5)"), "0W 0");
+        assert_eq!(eval_str(b"+/0W 1 2"), "0W");
+        assert_eq!(eval_str(b"sum 0W 1 2"), "0W");
+        assert_eq!(eval_str(b"-0N"), "0N");
+        // nulls stay null
+        assert_eq!(eval_str(b"0N+1"), "0N");
+        assert_eq!(eval_str(b"0N*2"), "0N");
+        assert_eq!(eval_str(b"-1*0N"), "0N");
+        assert_eq!(eval_str(b"0W+0N"), "0N");
+        assert_eq!(eval_str(b"1 2 0N+1"), "2 3 0N");
+        assert_eq!(eval_str(b"1+0N 2"), "0N 3");
+        assert_eq!(eval_str(b"0N 0W-1 0N"), "0N 0N");
+    }
+
+    #[test]
+    fn error_span_test() {
+        let span = |src: &[u8]| {
+            let e = eval(src).unwrap_err();
+            (e.location, e.end)
+        };
+        assert_eq!(span(b"1 2+`a"), (3, Some(6)));
+        assert_eq!(span(b"(1 2+3 4 5)*2"), (4, Some(10)));
+        // errors from inside a function keep their own location
+        assert_eq!(span(b"{[a] a+`b}[1]"), (6, Some(9)));
+        assert_eq!(span(b"spanf:{[a] a+`b}; 1+spanf 2"), (12, Some(15)));
+        // only dyadic verbs have a span
+        assert_eq!(span(b"-`a"), (0, None));
+        assert_eq!(span(b"spanundefined+1"), (0, None));
+    }
+
+    #[test]
+    fn verb_error_test() {
+        let message = |src: &[u8]| eval(src).unwrap_err().message();
+        assert_eq!(message(b"^1 2"), "Nyi: monadic ^");
+        assert_eq!(message(b"`a#1 2"), "Type: dyadic #");
+        assert_eq!(message(b"(+) . 1 2 3"), "Rank: triadic +");
+        // errors from inside a function name the verb that raised them
+        assert_eq!(message(b"verberrf:{x#1 2}; verberrf`a"), "Type: dyadic #");
+        // other codes are not about the verb
+        assert_eq!(message(b"1 2+3 4 5"), "Length");
+        assert!(message(b"verberrundefined").starts_with("UndefinedVariable("));
+    }
+
+    #[test]
+    fn error_kind_test() {
+        let code = |src: &[u8]| eval(src).unwrap_err().code;
+        // too many arguments
+        for src in [
+            &b"|[1;2;3]"[..],
+            b"~[1;2;3]",
+            b"?[1;2;3;4]",
+            b"+[1;2;3]",
+            b".[+;1;2;3;4]",
+        ] {
+            assert!(matches!(code(src), RuntimeErrorCode::Rank), "{:?}", src);
+        }
+        // arguments of the wrong type
+        for src in [&b"1.5!1 2"[..], b"\"a\"$1", b"`a#1 2", b"3[1]"] {
+            assert!(matches!(code(src), RuntimeErrorCode::Type), "{:?}", src);
+        }
+        // lists that don't conform
+        for src in [&b"1 2+3 4 5"[..], b"+(1 2;3 4 5)", b"1 2=1 2 3"] {
+            assert!(matches!(code(src), RuntimeErrorCode::Length), "{:?}", src);
+        }
+        // forms of a verb still to be written
+        for src in [&b"&1 0 1"[..], b"^1 2", b"=1 1 2", b"-2!1 2 3", b"3$\"a\""] {
+            assert!(matches!(code(src), RuntimeErrorCode::Nyi), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn implicit_rank_test() {
+        let code = |src: &[u8]| eval(src).unwrap_err().code;
+        // implicit parameters go up to the last of x y z the body uses
+        assert!(matches!(code(b"{x+y}[1;2;3]"), RuntimeErrorCode::Rank));
+        assert!(matches!(code(b"{x}[1;2]"), RuntimeErrorCode::Rank));
+        assert!(matches!(code(b"{x+y} . 1 2 3"), RuntimeErrorCode::Rank));
+        assert!(matches!(
+            code(b"rankf:{x+y}; rankf . 1 2 3"),
+            RuntimeErrorCode::Rank
+        ));
+        assert_eq!(eval_str(b"{x+z}[1;2;3]"), "4");
+        assert_eq!(eval_str(b"{1}[]"), "1");
+    }
+
+    #[test]
+    fn by_test() {
+        assert_eq!(eval_str(b"by[sum;1 1 2;10 20 30]"), "1| 30\n2| 30");
+        assert_eq!(eval_str(b"by[#:;`b`a`b;1 2 3]"), "b| 2\na| 1");
+        assert_eq!(eval_str(b"by[{x};3 1 3;1 2 3]"), "3| 1 3\n1| ,2");
+        assert_eq!(
+            eval_str(b"byk:1 1 2; byv:1.5 2.5 4; by[avg;byk;byv]"),
+            "1| 2\n2| 4"
+        );
+        assert!(matches!(
+            eval(b"by[sum;1 1;1 2 3]").unwrap_err().code,
+            RuntimeErrorCode::Length
+        ));
+        assert!(matches!(
+            eval(b"by[sum;1;2]").unwrap_err().code,
+            RuntimeErrorCode::Type
+        ));
+    }
 }
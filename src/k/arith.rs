@@ -1,18 +1,138 @@
 use std::ops::{Add, Deref, Div, Mul, Neg, Sub};
+use std::sync::Arc;
+#[cfg(feature = "parallel")]
+use std::thread;
 
 use crate::error::RuntimeErrorCode;
-use crate::k::{KResult, K, K0};
+use crate::k::cast::int_to_float;
+use crate::k::{conform, list, KResult, List, Shape, INF_INT, K, K0, NULL_INT};
 
+// symbols are not numbers, anything else is a plain type error
+pub(super) fn type_error(operands: &[&K]) -> RuntimeErrorCode {
+    match operands
+        .iter()
+        .any(|x| matches!(***x, K0::Sym(_) | K0::SymList(_) | K0::Name(_)))
+    {
+        true => RuntimeErrorCode::NonNumeric,
+        false => RuntimeErrorCode::Type,
+    }
+}
+
+fn has_bools(x: &K) -> bool {
+    match x.deref() {
+        K0::Bool(_) | K0::BoolList(_) => true,
+        K0::GenList(x) => x.iter().any(has_bools),
+        _ => false,
+    }
+}
+
+// bools are the ints 0 and 1 in arithmetic
+pub(super) fn bools_to_ints(x: &K) -> K {
+    match x.deref() {
+        K0::Bool(b) => K0::Int(*b as i64).into(),
+        K0::BoolList(b) => K0::IntList(b.iter().map(|&b| b as i64).collect()).into(),
+        K0::GenList(v) if has_bools(x) => v.iter().map(bools_to_ints).collect::<Vec<_>>().into(),
+        _ => x.clone(),
+    }
+}
+
+// apply f with bools promoted to ints, None if neither side has any
+fn bool_arith(x: &K, y: &K, f: fn(&K, &K) -> KResult) -> Option<KResult> {
+    match has_bools(x) || has_bools(y) {
+        true => Some(f(&bools_to_ints(x), &bools_to_ints(y))),
+        false => None,
+    }
+}
+
+// lists at least this long are split across threads
+#[cfg(feature = "parallel")]
+const PARALLEL_LEN: usize = 100_000;
+
+// f of the pairs of items of two lists of the same length
+fn zip_map<T, U, V, F>(x: &[T], y: &[U], f: F) -> List<V>
+where
+    T: Copy + Sync,
+    U: Copy + Sync,
+    V: Send,
+    F: Fn(T, U) -> V + Sync,
+{
+    #[cfg(feature = "parallel")]
+    if x.len() >= PARALLEL_LEN {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        if threads > 1 {
+            return parallel_zip_map(x, y, f, threads);
+        }
+    }
+    x.iter().zip(y).map(|(&i, &j)| f(i, j)).collect()
+}
+
+// zip_map on one chunk of the lists per thread, joined back in order
+#[cfg(feature = "parallel")]
+pub(super) fn parallel_zip_map<T, U, V, F>(x: &[T], y: &[U], f: F, threads: usize) -> List<V>
+where
+    T: Copy + Sync,
+    U: Copy + Sync,
+    V: Send,
+    F: Fn(T, U) -> V + Sync,
+{
+    let chunk = x.len() / threads + 1;
+    let f = &f;
+    thread::scope(|s| {
+        let workers = x
+            .chunks(chunk)
+            .zip(y.chunks(chunk))
+            .map(|(x, y)| {
+                s.spawn(move || x.iter().zip(y).map(|(&i, &j)| f(i, j)).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("worker thread panicked"))
+            .collect()
+    })
+}
+
+// int arithmetic keeps nulls, and otherwise saturates at 0W and -0W instead of overflowing into
+// the null
+fn saturate(x: i64, y: i64, res: Option<i64>, positive: bool) -> i64 {
+    match res {
+        _ if x == NULL_INT || y == NULL_INT => NULL_INT,
+        Some(r) => r.max(-INF_INT),
+        None if positive => INF_INT,
+        None => -INF_INT,
+    }
+}
+
+pub(super) fn add_int(x: i64, y: i64) -> i64 {
+    saturate(x, y, x.checked_add(y), y > 0)
+}
+
+fn sub_int(x: i64, y: i64) -> i64 {
+    saturate(x, y, x.checked_sub(y), y < 0)
+}
+
+fn mul_int(x: i64, y: i64) -> i64 {
+    saturate(x, y, x.checked_mul(y), (x < 0) == (y < 0))
+}
+
+// replace every item of a list with f of it
+fn update<T: Copy>(x: &mut [T], f: impl Fn(T) -> T) {
+    for i in x {
+        *i = f(*i);
+    }
+}
+
+// ints combine with $int, anything with a float with $op
 macro_rules! impl_i64_arith {
-    ($trait: tt, $method: tt, $op: tt) => {
+    ($trait: tt, $method: tt, $op: tt, $int: ident) => {
         impl $trait<i64> for &K {
             type Output = KResult;
 
             fn $method(self, rhs: i64) -> Self::Output {
                 match self.deref() {
-                    K0::Int(x) => Ok(K0::Int(x $op rhs).into()),
+                    K0::Int(x) => Ok(K0::Int($int(*x, rhs)).into()),
                     K0::Float(x) => Ok(K0::Float(x $op rhs as f64).into()),
-                    K0::IntList(x) => Ok(K0::IntList(x.iter().map(|i| i $op rhs).collect()).into()),
+                    K0::IntList(x) => Ok(K0::IntList(x.iter().map(|&i| $int(i, rhs)).collect()).into()),
                     K0::FloatList(x) => {
                         let rhs = rhs as f64;
                         Ok(K0::FloatList(x.iter().map(|i| i $op rhs).collect()).into())
@@ -22,7 +142,7 @@ macro_rules! impl_i64_arith {
                         .map(|i| i $op rhs)
                         .collect::<Result<Vec<_>, _>>()?
                         .into()),
-                    _ => Err(RuntimeErrorCode::Type),
+                    _ => Err(type_error(&[self])),
                 }
             }
         }
@@ -32,9 +152,9 @@ macro_rules! impl_i64_arith {
 
             fn $method(self, rhs: &K) -> Self::Output {
                 match rhs.deref() {
-                    K0::Int(x) => Ok(K0::Int(self $op x).into()),
+                    K0::Int(x) => Ok(K0::Int($int(self, *x)).into()),
                     K0::Float(x) => Ok(K0::Float(self as f64 $op x).into()),
-                    K0::IntList(x) => Ok(K0::IntList(x.iter().map(|i| self $op i).collect()).into()),
+                    K0::IntList(x) => Ok(K0::IntList(x.iter().map(|&i| $int(self, i)).collect()).into()),
                     K0::FloatList(x) => {
                         let lhs = self as f64;
                         Ok(K0::FloatList(x.iter().map(|i| lhs $op i).collect()).into())
@@ -44,10 +164,43 @@ macro_rules! impl_i64_arith {
                         .map(|i| self $op i)
                         .collect::<Result<Vec<_>, _>>()?
                         .into()),
-                    _ => Err(RuntimeErrorCode::Type),
+                    _ => Err(type_error(&[rhs])),
                 }
             }
         }
+
+        // a list nobody else holds is updated in place
+        impl $trait<i64> for K {
+            type Output = KResult;
+
+            fn $method(mut self, rhs: i64) -> Self::Output {
+                match Arc::get_mut(&mut self.0) {
+                    Some(K0::IntList(x)) => update(x, |i| $int(i, rhs)),
+                    Some(K0::FloatList(x)) => {
+                        let rhs = rhs as f64;
+                        update(x, |i| i $op rhs)
+                    }
+                    _ => return &self $op rhs,
+                }
+                Ok(self)
+            }
+        }
+
+        impl $trait<K> for i64 {
+            type Output = KResult;
+
+            fn $method(self, mut rhs: K) -> Self::Output {
+                match Arc::get_mut(&mut rhs.0) {
+                    Some(K0::IntList(x)) => update(x, |i| $int(self, i)),
+                    Some(K0::FloatList(x)) => {
+                        let lhs = self as f64;
+                        update(x, |i| lhs $op i)
+                    }
+                    _ => return self $op &rhs,
+                }
+                Ok(rhs)
+            }
+        }
     };
 }
 
@@ -71,7 +224,7 @@ macro_rules! impl_f64_arith {
                         .map(|i| i $op rhs)
                         .collect::<Result<Vec<_>, _>>()?
                         .into()),
-                    _ => Err(RuntimeErrorCode::Type),
+                    _ => Err(type_error(&[self])),
                 }
             }
         }
@@ -94,52 +247,89 @@ macro_rules! impl_f64_arith {
                         .map(|i| self $op i)
                         .collect::<Result<Vec<_>, _>>()?
                         .into()),
-                    _ => Err(RuntimeErrorCode::Type),
+                    _ => Err(type_error(&[rhs])),
+                }
+            }
+        }
+
+        // a float list nobody else holds is updated in place
+        impl $trait<f64> for K {
+            type Output = KResult;
+
+            fn $method(mut self, rhs: f64) -> Self::Output {
+                match Arc::get_mut(&mut self.0) {
+                    Some(K0::FloatList(x)) => update(x, |i| i $op rhs),
+                    _ => return &self $op rhs,
                 }
+                Ok(self)
+            }
+        }
+
+        impl $trait<K> for f64 {
+            type Output = KResult;
+
+            fn $method(self, mut rhs: K) -> Self::Output {
+                match Arc::get_mut(&mut rhs.0) {
+                    Some(K0::FloatList(x)) => update(x, |i| self $op i),
+                    _ => return self $op &rhs,
+                }
+                Ok(rhs)
             }
         }
     };
 }
 
-macro_rules! impl_k_arith {
+// an atom with a numeric list goes through the in place arithmetic above, anything else through
+// the arithmetic on references
+macro_rules! impl_owned_arith {
     ($trait: tt, $method: tt, $op: tt) => {
+        impl $trait for K {
+            type Output = KResult;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                match (self.deref(), rhs.deref()) {
+                    (K0::Int(x), K0::IntList(_) | K0::FloatList(_)) => *x $op rhs,
+                    (K0::Float(x), K0::IntList(_) | K0::FloatList(_)) => *x $op rhs,
+                    (K0::IntList(_) | K0::FloatList(_), &K0::Int(y)) => self $op y,
+                    (K0::IntList(_) | K0::FloatList(_), &K0::Float(y)) => self $op y,
+                    _ => &self $op &rhs,
+                }
+            }
+        }
+    };
+}
+
+macro_rules! impl_k_arith {
+    ($trait: tt, $method: tt, $op: tt, $int: ident $(, $chars: ident)?) => {
         impl $trait for &K {
             type Output = KResult;
 
             fn $method(self, rhs: Self) -> Self::Output {
+                if let Some(res) = bool_arith(self, rhs, |x, y| x $op y) {
+                    return res;
+                }
+                $(
+                    if let Some(res) = $chars(self, rhs) {
+                        return res;
+                    }
+                )?
+                conform(self, rhs)?;
                 match (self.deref(), rhs.deref()) {
                     (K0::Int(x), _) => *x $op rhs,
                     (K0::Float(x), _) => *x $op rhs,
 
                     (K0::IntList(x), K0::IntList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(K0::IntList(x.iter().zip(y).map(|(i, j)| i $op j).collect()).into())
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(K0::IntList(zip_map(x, y, $int)).into())
                     }
                     (K0::IntList(x), K0::FloatList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(
-                                K0::FloatList(
-                                    x.iter().zip(y).map(|(&i, j)| i as f64 $op j).collect(),
-                                )
-                                .into(),
-                            )
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(K0::FloatList(zip_map(x, y, |i, j| i as f64 $op j)).into())
                     }
                     (K0::IntList(x), K0::GenList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(x.iter()
-                                .zip(y)
-                                .map(|(&i, j)| i $op j)
-                                .collect::<Result<Vec<_>, _>>()?
-                                .into())
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(x.iter()
+                            .zip(y)
+                            .map(|(&i, j)| i $op j)
+                            .collect::<Result<Vec<_>, _>>()?
+                            .into())
                     }
                     (K0::IntList(x), _) => Ok(x
                         .iter()
@@ -148,34 +338,17 @@ macro_rules! impl_k_arith {
                         .into()),
 
                     (K0::FloatList(x), K0::IntList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(
-                                K0::FloatList(
-                                    x.iter().zip(y).map(|(i, &j)| i $op j as f64).collect(),
-                                )
-                                .into(),
-                            )
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(K0::FloatList(zip_map(x, y, |i, j| i $op j as f64)).into())
                     }
                     (K0::FloatList(x), K0::FloatList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(K0::FloatList(x.iter().zip(y).map(|(i, j)| i $op j).collect()).into())
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(K0::FloatList(zip_map(x, y, |i, j| i $op j)).into())
                     }
                     (K0::FloatList(x), K0::GenList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(x.iter()
-                                .zip(y)
-                                .map(|(&i, j)| i $op j)
-                                .collect::<Result<Vec<_>, _>>()?
-                                .into())
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(x.iter()
+                            .zip(y)
+                            .map(|(&i, j)| i $op j)
+                            .collect::<Result<Vec<_>, _>>()?
+                            .into())
                     }
                     (K0::FloatList(x), _) => Ok(x
                         .iter()
@@ -184,37 +357,25 @@ macro_rules! impl_k_arith {
                         .into()),
 
                     (K0::GenList(x), K0::IntList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(x.iter()
-                                .zip(y)
-                                .map(|(i, &j)| i $op j)
-                                .collect::<Result<Vec<_>, _>>()?
-                                .into())
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(x.iter()
+                            .zip(y)
+                            .map(|(i, &j)| i $op j)
+                            .collect::<Result<Vec<_>, _>>()?
+                            .into())
                     }
                     (K0::GenList(x), K0::FloatList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(x.iter()
-                                .zip(y)
-                                .map(|(i, &j)| i $op j)
-                                .collect::<Result<Vec<_>, _>>()?
-                                .into())
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(x.iter()
+                            .zip(y)
+                            .map(|(i, &j)| i $op j)
+                            .collect::<Result<Vec<_>, _>>()?
+                            .into())
                     }
                     (K0::GenList(x), K0::GenList(y)) => {
-                        if x.len() == y.len() {
-                            Ok(x.iter()
-                                .zip(y)
-                                .map(|(i, j)| i $op j)
-                                .collect::<Result<Vec<_>, _>>()?
-                                .into())
-                        } else {
-                            Err(RuntimeErrorCode::Length)
-                        }
+                        Ok(x.iter()
+                            .zip(y)
+                            .map(|(i, j)| i $op j)
+                            .collect::<Result<Vec<_>, _>>()?
+                            .into())
                     }
                     (K0::GenList(x), _) => Ok(x
                         .iter()
@@ -222,26 +383,73 @@ macro_rules! impl_k_arith {
                         .collect::<Result<Vec<_>, _>>()?
                         .into()),
 
-                    (_, _) => Err(RuntimeErrorCode::Type),
+                    (_, _) => Err(type_error(&[self, rhs])),
                 }
             }
         }
     };
 }
 
-impl_i64_arith!(Add, add, +);
+fn is_char(x: &K) -> bool {
+    matches!(x.deref(), K0::Char(_) | K0::CharList(_))
+}
+
+// chars as their byte values
+fn char_to_int(x: &K) -> K {
+    match x.deref() {
+        K0::Char(c) => K0::Int(*c as i64).into(),
+        K0::CharList(c) => K0::IntList(c.iter().map(|&c| c as i64).collect()).into(),
+        _ => x.clone(),
+    }
+}
+
+// ints back to chars, wrapping around modulo 256
+fn int_to_char(x: &K) -> K {
+    match x.deref() {
+        K0::Int(i) => K0::Char(*i as u8).into(),
+        K0::IntList(i) => K0::CharList(i.iter().map(|&i| i as u8).collect()).into(),
+        _ => x.clone(),
+    }
+}
+
+// chars and ints add and subtract as byte values giving chars, the difference of two chars is
+// an int. None if neither side is a char.
+fn char_arith(x: &K, y: &K, f: fn(&K, &K) -> KResult, diff: bool) -> Option<KResult> {
+    let is_int = |k: &K| matches!(k.deref(), K0::Int(_) | K0::IntList(_));
+    Some(match (is_char(x), is_char(y)) {
+        (false, false) => return None,
+        (true, true) if diff => f(&char_to_int(x), &char_to_int(y)),
+        (true, false) if is_int(y) => f(&char_to_int(x), y).map(|k| int_to_char(&k)),
+        (false, true) if is_int(x) => f(x, &char_to_int(y)).map(|k| int_to_char(&k)),
+        _ => Err(type_error(&[x, y])),
+    })
+}
+
+fn add_chars(x: &K, y: &K) -> Option<KResult> {
+    char_arith(x, y, |a, b| a + b, false)
+}
+
+fn sub_chars(x: &K, y: &K) -> Option<KResult> {
+    char_arith(x, y, |a, b| a - b, true)
+}
+
+impl_i64_arith!(Add, add, +, add_int);
 impl_f64_arith!(Add, add, +);
-impl_k_arith!(Add, add, +);
+impl_k_arith!(Add, add, +, add_int, add_chars);
+impl_owned_arith!(Add, add, +);
 
-impl_i64_arith!(Sub, sub, -);
+impl_i64_arith!(Sub, sub, -, sub_int);
 impl_f64_arith!(Sub, sub, -);
-impl_k_arith!(Sub, sub, -);
+impl_k_arith!(Sub, sub, -, sub_int, sub_chars);
+impl_owned_arith!(Sub, sub, -);
 
-impl_i64_arith!(Mul, mul, *);
+impl_i64_arith!(Mul, mul, *, mul_int);
 impl_f64_arith!(Mul, mul, *);
-impl_k_arith!(Mul, mul, *);
+impl_k_arith!(Mul, mul, *, mul_int);
+impl_owned_arith!(Mul, mul, *);
 
 impl_f64_arith!(Div, div, /);
+impl_owned_arith!(Div, div, /);
 
 // convert ints to floats for division
 impl Div<i64> for &K {
@@ -260,45 +468,46 @@ impl Div<&K> for i64 {
     }
 }
 
+impl Div<i64> for K {
+    type Output = KResult;
+
+    fn div(self, rhs: i64) -> Self::Output {
+        self / rhs as f64
+    }
+}
+
+impl Div<K> for i64 {
+    type Output = KResult;
+
+    fn div(self, rhs: K) -> Self::Output {
+        self as f64 / rhs
+    }
+}
+
 impl Div for &K {
     type Output = KResult;
 
     fn div(self, rhs: Self) -> Self::Output {
+        if let Some(res) = bool_arith(self, rhs, |x, y| x / y) {
+            return res;
+        }
+        conform(self, rhs)?;
         match (self.deref(), rhs.deref()) {
             (K0::Int(x), _) => *x / rhs,
             (K0::Float(x), _) => *x / rhs,
 
             (K0::IntList(x), K0::IntList(y)) => {
-                if x.len() == y.len() {
-                    Ok(K0::FloatList(
-                        x.iter()
-                            .zip(y)
-                            .map(|(&i, &j)| i as f64 / j as f64)
-                            .collect(),
-                    )
-                    .into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
+                Ok(K0::FloatList(zip_map(x, y, |i, j| i as f64 / j as f64)).into())
             }
             (K0::IntList(x), K0::FloatList(y)) => {
-                if x.len() == y.len() {
-                    Ok(K0::FloatList(x.iter().zip(y).map(|(&i, j)| i as f64 / j).collect()).into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
-            }
-            (K0::IntList(x), K0::GenList(y)) => {
-                if x.len() == y.len() {
-                    Ok(x.iter()
-                        .zip(y)
-                        .map(|(&i, j)| i as f64 / j)
-                        .collect::<Result<Vec<_>, _>>()?
-                        .into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
+                Ok(K0::FloatList(zip_map(x, y, |i, j| i as f64 / j)).into())
             }
+            (K0::IntList(x), K0::GenList(y)) => Ok(x
+                .iter()
+                .zip(y)
+                .map(|(&i, j)| i as f64 / j)
+                .collect::<Result<Vec<_>, _>>()?
+                .into()),
             (K0::IntList(x), _) => Ok(x
                 .iter()
                 .map(|&i| i / rhs)
@@ -306,76 +515,48 @@ impl Div for &K {
                 .into()),
 
             (K0::FloatList(x), K0::IntList(y)) => {
-                if x.len() == y.len() {
-                    Ok(K0::FloatList(x.iter().zip(y).map(|(i, &j)| i / j as f64).collect()).into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
+                Ok(K0::FloatList(zip_map(x, y, |i, j| i / j as f64)).into())
             }
             (K0::FloatList(x), K0::FloatList(y)) => {
-                if x.len() == y.len() {
-                    Ok(K0::FloatList(x.iter().zip(y).map(|(i, j)| i / j).collect()).into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
-            }
-            (K0::FloatList(x), K0::GenList(y)) => {
-                if x.len() == y.len() {
-                    Ok(x.iter()
-                        .zip(y)
-                        .map(|(&i, j)| i / j)
-                        .collect::<Result<Vec<_>, _>>()?
-                        .into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
+                Ok(K0::FloatList(zip_map(x, y, |i, j| i / j)).into())
             }
+            (K0::FloatList(x), K0::GenList(y)) => Ok(x
+                .iter()
+                .zip(y)
+                .map(|(&i, j)| i / j)
+                .collect::<Result<Vec<_>, _>>()?
+                .into()),
             (K0::FloatList(x), _) => Ok(x
                 .iter()
                 .map(|&i| i / rhs)
                 .collect::<Result<Vec<_>, _>>()?
                 .into()),
 
-            (K0::GenList(x), K0::IntList(y)) => {
-                if x.len() == y.len() {
-                    Ok(x.iter()
-                        .zip(y)
-                        .map(|(i, &j)| i / j as f64)
-                        .collect::<Result<Vec<_>, _>>()?
-                        .into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
-            }
-            (K0::GenList(x), K0::FloatList(y)) => {
-                if x.len() == y.len() {
-                    Ok(x.iter()
-                        .zip(y)
-                        .map(|(i, &j)| i / j)
-                        .collect::<Result<Vec<_>, _>>()?
-                        .into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
-            }
-            (K0::GenList(x), K0::GenList(y)) => {
-                if x.len() == y.len() {
-                    Ok(x.iter()
-                        .zip(y)
-                        .map(|(i, j)| i / j)
-                        .collect::<Result<Vec<_>, _>>()?
-                        .into())
-                } else {
-                    Err(RuntimeErrorCode::Length)
-                }
-            }
+            (K0::GenList(x), K0::IntList(y)) => Ok(x
+                .iter()
+                .zip(y)
+                .map(|(i, &j)| i / j as f64)
+                .collect::<Result<Vec<_>, _>>()?
+                .into()),
+            (K0::GenList(x), K0::FloatList(y)) => Ok(x
+                .iter()
+                .zip(y)
+                .map(|(i, &j)| i / j)
+                .collect::<Result<Vec<_>, _>>()?
+                .into()),
+            (K0::GenList(x), K0::GenList(y)) => Ok(x
+                .iter()
+                .zip(y)
+                .map(|(i, j)| i / j)
+                .collect::<Result<Vec<_>, _>>()?
+                .into()),
             (K0::GenList(x), _) => Ok(x
                 .iter()
                 .map(|i| i / rhs)
                 .collect::<Result<Vec<_>, _>>()?
                 .into()),
 
-            (_, _) => Err(RuntimeErrorCode::Type),
+            (_, _) => Err(type_error(&[self, rhs])),
         }
     }
 }
@@ -385,12 +566,165 @@ impl Neg for &K {
 
     fn neg(self) -> Self::Output {
         match self.deref() {
-            K0::Int(x) => Ok(K0::Int(-x).into()),
+            K0::Bool(_) | K0::BoolList(_) => -&bools_to_ints(self),
+            // the null has no negation and stays null
+            K0::Int(x) => Ok(K0::Int(x.wrapping_neg()).into()),
             K0::Float(x) => Ok(K0::Float(-x).into()),
-            K0::IntList(x) => Ok(K0::IntList(x.iter().map(|i| -i).collect()).into()),
+            K0::IntList(x) => Ok(K0::IntList(x.iter().map(|i| i.wrapping_neg()).collect()).into()),
             K0::FloatList(x) => Ok(K0::FloatList(x.iter().map(|i| -i).collect()).into()),
             K0::GenList(x) => Ok(x.iter().map(|i| -i).collect::<Result<Vec<_>, _>>()?.into()),
-            _ => Err(RuntimeErrorCode::Type),
+            _ => Err(type_error(&[self])),
+        }
+    }
+}
+
+// apply a dyadic numeric function item-wise, ints are promoted to floats when mixed with floats
+fn numeric_dyad(x: &K, y: &K, fi: fn(i64, i64) -> i64, ff: fn(f64, f64) -> f64) -> KResult {
+    if has_bools(x) || has_bools(y) {
+        return numeric_dyad(&bools_to_ints(x), &bools_to_ints(y), fi, ff);
+    }
+    let shape = conform(x, y)?;
+    Ok(match (x.deref(), y.deref()) {
+        (K0::Int(a), K0::Int(b)) => fi(*a, *b).into(),
+        (K0::Int(a), K0::Float(b)) => ff(*a as f64, *b).into(),
+        (K0::Float(a), K0::Int(b)) => ff(*a, *b as f64).into(),
+        (K0::Float(a), K0::Float(b)) => ff(*a, *b).into(),
+
+        (K0::IntList(a), K0::Int(b)) => K0::IntList(a.iter().map(|&i| fi(i, *b)).collect()).into(),
+        (K0::Int(a), K0::IntList(b)) => K0::IntList(b.iter().map(|&j| fi(*a, j)).collect()).into(),
+        (K0::IntList(a), K0::IntList(b)) => {
+            K0::IntList(a.iter().zip(b).map(|(&i, &j)| fi(i, j)).collect()).into()
+        }
+        (K0::FloatList(a), K0::Float(b)) => {
+            K0::FloatList(a.iter().map(|&i| ff(i, *b)).collect()).into()
         }
+        (K0::Float(a), K0::FloatList(b)) => {
+            K0::FloatList(b.iter().map(|&j| ff(*a, j)).collect()).into()
+        }
+        (K0::FloatList(a), K0::FloatList(b)) => {
+            K0::FloatList(a.iter().zip(b).map(|(&i, &j)| ff(i, j)).collect()).into()
+        }
+
+        // mixed or general lists go item by item
+        _ => match shape {
+            Shape::Atoms => return Err(type_error(&[x, y])),
+            Shape::AtomList => y
+                .items()
+                .unwrap_or_default()
+                .iter()
+                .map(|j| numeric_dyad(x, j, fi, ff))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            Shape::ListAtom => x
+                .items()
+                .unwrap_or_default()
+                .iter()
+                .map(|i| numeric_dyad(i, y, fi, ff))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            Shape::Lists => x
+                .items()
+                .unwrap_or_default()
+                .iter()
+                .zip(y.items().unwrap_or_default().iter())
+                .map(|(i, j)| numeric_dyad(i, j, fi, ff))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+        },
+    })
+}
+
+// ints and bools as floats, with the int null and infinities as their float counterparts
+fn to_floats(x: &K) -> K {
+    match bools_to_ints(x).deref() {
+        K0::Int(i) => int_to_float(*i).into(),
+        K0::IntList(v) => K0::FloatList(v.iter().map(|&i| int_to_float(i)).collect()).into(),
+        K0::GenList(v) => K0::GenList(v.iter().map(to_floats).collect()).into(),
+        _ => x.clone(),
+    }
+}
+
+// x&y and x|y of bools stay bools, None unless both sides are bools
+fn bool_dyad(x: &K, y: &K, f: fn(bool, bool) -> bool) -> Option<KResult> {
+    let bits = |k: &K| match k.deref() {
+        K0::Bool(b) => Some(list![*b]),
+        K0::BoolList(b) => Some(b.clone()),
+        _ => None,
+    };
+    let (a, b) = (bits(x)?, bits(y)?);
+    Some(conform(x, y).map(|shape| match shape {
+        Shape::Atoms => f(a[0], b[0]).into(),
+        Shape::AtomList => K0::BoolList(b.iter().map(|&j| f(a[0], j)).collect()).into(),
+        Shape::ListAtom => K0::BoolList(a.iter().map(|&i| f(i, b[0])).collect()).into(),
+        Shape::Lists => K0::BoolList(a.iter().zip(&b).map(|(&i, &j)| f(i, j)).collect()).into(),
+    }))
+}
+
+// nulls (NaN) are smaller than any other float, like the int null
+fn float_min(x: f64, y: f64) -> f64 {
+    if x <= y || x.is_nan() {
+        x
+    } else {
+        y
+    }
+}
+
+fn float_max(x: f64, y: f64) -> f64 {
+    if x >= y || y.is_nan() {
+        x
+    } else {
+        y
+    }
+}
+
+impl K {
+    // %x: square root as a float, NaN for negatives and int nulls
+    pub fn sqrt(&self) -> KResult {
+        let int = |i: i64| match i {
+            NULL_INT => f64::NAN,
+            i => (i as f64).sqrt(),
+        };
+        match self.deref() {
+            K0::Bool(_) | K0::BoolList(_) => bools_to_ints(self).sqrt(),
+            K0::Int(x) => Ok(int(*x).into()),
+            K0::Float(x) => Ok(x.sqrt().into()),
+            K0::IntList(x) => Ok(K0::FloatList(x.iter().map(|&i| int(i)).collect()).into()),
+            K0::FloatList(x) => Ok(K0::FloatList(x.iter().map(|f| f.sqrt()).collect()).into()),
+            K0::GenList(x) => Ok(x.iter().map(K::sqrt).collect::<Result<Vec<_>, _>>()?.into()),
+            _ => Err(type_error(&[self])),
+        }
+    }
+
+    pub fn min(&self, rhs: &K) -> KResult {
+        bool_dyad(self, rhs, |x, y| x & y)
+            .unwrap_or_else(|| numeric_dyad(self, rhs, i64::min, float_min))
+    }
+
+    pub fn max(&self, rhs: &K) -> KResult {
+        bool_dyad(self, rhs, |x, y| x | y)
+            .unwrap_or_else(|| numeric_dyad(self, rhs, i64::max, float_max))
+    }
+
+    // x^y: x to the power y, always a float
+    pub fn power(&self, rhs: &K) -> KResult {
+        numeric_dyad(
+            &to_floats(self),
+            &to_floats(rhs),
+            |_, _| unreachable!("only floats"),
+            f64::powf,
+        )
+    }
+
+    // x!y: the non-negative remainder of y divided by x, nulls stay null
+    pub fn modulo(&self, rhs: &K) -> KResult {
+        numeric_dyad(
+            self,
+            rhs,
+            |x, y| match y {
+                NULL_INT => NULL_INT,
+                _ => y.rem_euclid(x),
+            },
+            |x, y| y.rem_euclid(x),
+        )
     }
 }
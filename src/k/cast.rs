@@ -0,0 +1,103 @@
+use std::ops::Deref;
+
+use crate::error::RuntimeErrorCode;
+use crate::k::{KResult, INF_INT, K, K0, NULL_INT};
+use crate::sym::Sym;
+
+#[derive(Copy, Clone)]
+enum Target {
+    Bool,
+    Char,
+    Int,
+    Float,
+}
+
+impl Target {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"b" | b"boolean" => Some(Self::Bool),
+            b"c" | b"char" => Some(Self::Char),
+            b"i" | b"int" => Some(Self::Int),
+            b"f" | b"float" => Some(Self::Float),
+            _ => None,
+        }
+    }
+}
+
+// floats truncate towards zero, with nulls and infinities going to their int sentinels
+fn float_to_int(f: f64) -> i64 {
+    match f {
+        f if f.is_nan() => NULL_INT,
+        f if f == f64::INFINITY => INF_INT,
+        f if f == f64::NEG_INFINITY => -INF_INT,
+        f => f as i64,
+    }
+}
+
+pub(super) fn int_to_float(i: i64) -> f64 {
+    match i {
+        NULL_INT => f64::NAN,
+        INF_INT => f64::INFINITY,
+        i if i == -INF_INT => f64::NEG_INFINITY,
+        i => i as f64,
+    }
+}
+
+fn cast_atom(x: &K0, target: Target) -> KResult {
+    Ok(match (target, x) {
+        (Target::Bool, K0::Bool(b)) => (*b).into(),
+        (Target::Bool, K0::Char(c)) => (*c != 0).into(),
+        (Target::Bool, K0::Int(i)) => (*i != 0).into(),
+        (Target::Bool, K0::Float(f)) => (*f != 0.0).into(),
+
+        (Target::Char, K0::Bool(b)) => (*b as u8).into(),
+        (Target::Char, K0::Char(c)) => (*c).into(),
+        // wrapping around modulo 256 like char arithmetic
+        (Target::Char, K0::Int(i)) => (*i as u8).into(),
+
+        (Target::Int, K0::Bool(b)) => (*b as i64).into(),
+        (Target::Int, K0::Char(c)) => (*c as i64).into(),
+        (Target::Int, K0::Int(i)) => (*i).into(),
+        (Target::Int, K0::Float(f)) => float_to_int(*f).into(),
+
+        (Target::Float, K0::Bool(b)) => (*b as u8 as f64).into(),
+        (Target::Float, K0::Char(c)) => (*c as f64).into(),
+        (Target::Float, K0::Int(i)) => int_to_float(*i).into(),
+        (Target::Float, K0::Float(f)) => (*f).into(),
+
+        _ => return Err(RuntimeErrorCode::Type),
+    })
+}
+
+fn cast_items(x: &K, target: Target) -> KResult {
+    match x.items() {
+        Some(items) => items
+            .iter()
+            .map(|i| cast_items(i, target))
+            .collect::<Result<Vec<_>, _>>()
+            .map(K::from),
+        None => cast_atom(x, target),
+    }
+}
+
+impl K {
+    // t$x: x item-wise as the type named by t, `b `c `i `f or their long names, and ` for
+    // symbols from strings and strings from symbols
+    pub fn cast(&self, target: Sym) -> KResult {
+        match target.as_bytes() {
+            b"" | b"symbol" => match self.deref() {
+                K0::Sym(s) => Ok(s.as_bytes().to_vec().into()),
+                K0::SymList(x) => Ok(x
+                    .iter()
+                    .map(|s| K0::CharList(s.as_bytes().into()).into())
+                    .collect::<Vec<K>>()
+                    .into()),
+                _ => self.to_sym(),
+            },
+            name => match Target::from_name(name) {
+                Some(t) => cast_items(self, t),
+                None => Err(RuntimeErrorCode::Domain),
+            },
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::ops::Deref;
+
+use crate::error::RuntimeErrorCode;
+use crate::k::{conform, KResult, Shape, K, K0};
+
+// nulls (NaN) sort before any other float and are equal to each other
+pub fn float_cmp(x: f64, y: f64) -> Ordering {
+    match (x.is_nan(), y.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => x.partial_cmp(&y).expect("not nan"),
+    }
+}
+
+// numbers compare by value, chars and symbols only with their own type
+fn cmp_atoms(x: &K0, y: &K0) -> Result<Ordering, RuntimeErrorCode> {
+    match (x, y) {
+        (K0::Bool(a), K0::Bool(b)) => Ok(a.cmp(b)),
+        // bools compare with numbers as 0 and 1
+        (K0::Bool(a), _) => cmp_atoms(&K0::Int(*a as i64), y),
+        (_, K0::Bool(b)) => cmp_atoms(x, &K0::Int(*b as i64)),
+        (K0::Int(a), K0::Int(b)) => Ok(a.cmp(b)),
+        (K0::Int(a), K0::Float(b)) => Ok(float_cmp(*a as f64, *b)),
+        (K0::Float(a), K0::Int(b)) => Ok(float_cmp(*a, *b as f64)),
+        (K0::Float(a), K0::Float(b)) => Ok(float_cmp(*a, *b)),
+        (K0::Char(a), K0::Char(b)) => Ok(a.cmp(b)),
+        (K0::Sym(a), K0::Sym(b)) => Ok(a.cmp(b)),
+        _ => Err(RuntimeErrorCode::Type),
+    }
+}
+
+// compare item-wise, producing 1b where pred holds for the ordering of the items and 0b otherwise
+fn compare(x: &K, y: &K, pred: fn(Ordering) -> bool) -> KResult {
+    let shape = conform(x, y)?;
+    Ok(match (x.deref(), y.deref()) {
+        (K0::IntList(a), K0::IntList(b)) => {
+            K0::BoolList(a.iter().zip(b).map(|(i, j)| pred(i.cmp(j))).collect()).into()
+        }
+        (K0::IntList(a), K0::Int(b)) => {
+            K0::BoolList(a.iter().map(|i| pred(i.cmp(b))).collect()).into()
+        }
+        (K0::Int(a), K0::IntList(b)) => {
+            K0::BoolList(b.iter().map(|j| pred(a.cmp(j))).collect()).into()
+        }
+        (K0::CharList(a), K0::CharList(b)) => {
+            K0::BoolList(a.iter().zip(b).map(|(i, j)| pred(i.cmp(j))).collect()).into()
+        }
+        _ => match shape {
+            Shape::Atoms => pred(cmp_atoms(x, y)?).into(),
+            Shape::AtomList => y
+                .items()
+                .unwrap_or_default()
+                .iter()
+                .map(|j| compare(x, j, pred))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            Shape::ListAtom => x
+                .items()
+                .unwrap_or_default()
+                .iter()
+                .map(|i| compare(i, y, pred))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            Shape::Lists => x
+                .items()
+                .unwrap_or_default()
+                .iter()
+                .zip(y.items().unwrap_or_default().iter())
+                .map(|(i, j)| compare(i, j, pred))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+        },
+    })
+}
+
+// indices that stably sort a list by cmp, reversed orderings keep equal items in input order
+fn grade_by<T, F>(x: &[T], descending: bool, cmp: F) -> Vec<usize>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let mut indices = (0..x.len()).collect::<Vec<_>>();
+    match descending {
+        false => indices.sort_by(|&i, &j| cmp(&x[i], &x[j])),
+        true => indices.sort_by(|&i, &j| cmp(&x[j], &x[i])),
+    }
+    indices
+}
+
+impl K {
+    fn grade_indices(&self, descending: bool) -> Result<Vec<usize>, RuntimeErrorCode> {
+        Ok(match self.deref() {
+            K0::BoolList(x) => grade_by(x, descending, bool::cmp),
+            K0::IntList(x) => grade_by(x, descending, i64::cmp),
+            K0::FloatList(x) => grade_by(x, descending, |a, b| float_cmp(*a, *b)),
+            K0::CharList(x) => grade_by(x, descending, u8::cmp),
+            K0::SymList(x) => {
+                // compare the names directly rather than taking the interner lock per comparison
+                let names = x.iter().map(|s| s.as_bytes()).collect::<Vec<_>>();
+                grade_by(&names, descending, |a, b| a.cmp(b))
+            }
+            K0::GenList(x) => {
+                if let Some(first) = x.first() {
+                    for k in x {
+                        cmp_atoms(first, k)?;
+                    }
+                }
+                grade_by(x, descending, |a, b| cmp_atoms(a, b).expect("uniform list"))
+            }
+            _ => return Err(RuntimeErrorCode::Type),
+        })
+    }
+
+    pub fn grade(&self, descending: bool) -> KResult {
+        let indices = self.grade_indices(descending)?;
+        Ok(K0::IntList(indices.into_iter().map(|i| i as i64).collect()).into())
+    }
+
+    // x@<x or x@>x, equal items keep their order either way
+    pub fn sort(&self, descending: bool) -> KResult {
+        Ok(self.select(&self.grade_indices(descending)?))
+    }
+
+    // indices of the items of x matching each needle of y (or y itself for an atom)
+    fn find_by<F>(&self, rhs: &K, f: F) -> KResult
+    where
+        F: Fn(&[K], &K) -> K,
+    {
+        let haystack = self.items().ok_or(RuntimeErrorCode::Type)?;
+        Ok(match rhs.items() {
+            Some(needles) => needles
+                .iter()
+                .map(|y| f(&haystack, y))
+                .collect::<Vec<_>>()
+                .into(),
+            None => f(&haystack, rhs),
+        })
+    }
+
+    // x?y: index of the first match of y in x, #x if there is none
+    pub fn find(&self, rhs: &K) -> KResult {
+        self.find_by(rhs, |x, y| {
+            (x.iter().position(|i| i.deep_eq(y)).unwrap_or(x.len()) as i64).into()
+        })
+    }
+
+    // indices of every match of y in x
+    pub fn find_all(&self, rhs: &K) -> KResult {
+        self.find_by(rhs, |x, y| {
+            K0::IntList(
+                (0..x.len())
+                    .filter(|&i| x[i].deep_eq(y))
+                    .map(|i| i as i64)
+                    .collect(),
+            )
+            .into()
+        })
+    }
+
+    // ?x: items of x without repeats, in order of first occurrence
+    pub fn distinct(&self) -> KResult {
+        let items = self.items().ok_or(RuntimeErrorCode::Type)?;
+        let mut indices: Vec<usize> = Vec::new();
+        // indices kept so far by hash, only items with the same hash need comparing
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, item) in items.iter().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            item.deep_hash(&mut hasher);
+            let kept = seen.entry(hasher.finish()).or_default();
+            if !kept.iter().any(|&j| items[j].deep_eq(item)) {
+                kept.push(i);
+                indices.push(i);
+            }
+        }
+        Ok(self.select(&indices))
+    }
+
+    // x within (lo;hi): 1b where lo <= x <= hi
+    pub fn within(&self, range: &K) -> KResult {
+        match range.items().as_deref() {
+            Some([lo, hi]) => {
+                compare(self, lo, Ordering::is_ge)?.min(&compare(self, hi, Ordering::is_le)?)
+            }
+            Some(_) => Err(RuntimeErrorCode::Length),
+            None => Err(RuntimeErrorCode::Type),
+        }
+    }
+
+    // x bin y: index of the last item of the sorted x not greater than y, -1 if there is none
+    pub fn bin(&self, rhs: &K) -> KResult {
+        let haystack = self.items().ok_or(RuntimeErrorCode::Type)?;
+        let search = |y: &K| -> KResult {
+            if let Some(first) = haystack.first() {
+                cmp_atoms(first, y)?;
+            }
+            let n = haystack.partition_point(|x| matches!(cmp_atoms(x, y), Ok(o) if o.is_le()));
+            Ok((n as i64 - 1).into())
+        };
+        match rhs.items() {
+            Some(needles) => needles
+                .iter()
+                .map(search)
+                .collect::<Result<Vec<_>, _>>()
+                .map(K::from),
+            None => search(rhs),
+        }
+    }
+
+    pub fn equal(&self, rhs: &K) -> KResult {
+        compare(self, rhs, Ordering::is_eq)
+    }
+
+    pub fn less(&self, rhs: &K) -> KResult {
+        compare(self, rhs, Ordering::is_lt)
+    }
+
+    pub fn greater(&self, rhs: &K) -> KResult {
+        compare(self, rhs, Ordering::is_gt)
+    }
+}
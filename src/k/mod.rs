@@ -1,14 +1,26 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::hint::unreachable_unchecked;
 use std::mem;
 use std::num::FpCategory;
 use std::ops::Deref;
+use std::str;
 use std::sync::Arc;
 
 use crate::error::RuntimeErrorCode;
+use crate::k::arith::bools_to_ints;
+use crate::parser::ASTNode;
 use crate::sym::Sym;
 
 mod arith;
+mod cast;
+mod cmp;
+pub mod ser;
+mod shape;
+mod stats;
+mod strings;
 
 #[derive(Copy, Clone, Debug)]
 pub enum Verb {
@@ -35,6 +47,38 @@ pub enum Verb {
     ZeroColon = 20,
     OneColon = 21,
     TwoColon = 22,
+    ColonColon = 23,
+}
+
+impl Verb {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Colon => ":",
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Star => "*",
+            Self::Percent => "%",
+            Self::And => "&",
+            Self::Pipe => "|",
+            Self::Caret => "^",
+            Self::Eq => "=",
+            Self::Lt => "<",
+            Self::Gt => ">",
+            Self::Dollar => "$",
+            Self::Comma => ",",
+            Self::Hash => "#",
+            Self::Underscore => "_",
+            Self::Tilde => "~",
+            Self::Bang => "!",
+            Self::Question => "?",
+            Self::At => "@",
+            Self::Dot => ".",
+            Self::ZeroColon => "0:",
+            Self::OneColon => "1:",
+            Self::TwoColon => "2:",
+            Self::ColonColon => "::",
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -47,9 +91,231 @@ pub enum Adverb {
     BackslashColon = 5,
 }
 
+impl Adverb {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Quote => "'",
+            Self::Slash => "/",
+            Self::Backslash => "\\",
+            Self::QuoteColon => "':",
+            Self::SlashColon => "/:",
+            Self::BackslashColon => "\\:",
+        }
+    }
+}
+
+impl TryFrom<u8> for Verb {
+    type Error = ();
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        Ok(match v {
+            0 => Self::Colon,
+            1 => Self::Plus,
+            2 => Self::Minus,
+            3 => Self::Star,
+            4 => Self::Percent,
+            5 => Self::And,
+            6 => Self::Pipe,
+            7 => Self::Caret,
+            8 => Self::Eq,
+            9 => Self::Lt,
+            10 => Self::Gt,
+            11 => Self::Dollar,
+            12 => Self::Comma,
+            13 => Self::Hash,
+            14 => Self::Underscore,
+            15 => Self::Tilde,
+            16 => Self::Bang,
+            17 => Self::Question,
+            18 => Self::At,
+            19 => Self::Dot,
+            20 => Self::ZeroColon,
+            21 => Self::OneColon,
+            22 => Self::TwoColon,
+            23 => Self::ColonColon,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl TryFrom<u8> for Adverb {
+    type Error = ();
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        Ok(match v {
+            0 => Self::Quote,
+            1 => Self::Slash,
+            2 => Self::Backslash,
+            3 => Self::QuoteColon,
+            4 => Self::SlashColon,
+            5 => Self::BackslashColon,
+            _ => return Err(()),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Builtin {
+    Asc,
+    Avg,
+    Bin,
+    By,
+    Config,
+    Cor,
+    Cov,
+    Desc,
+    Dev,
+    Do,
+    Ema,
+    Enlist,
+    FindAll,
+    Get,
+    If,
+    Load,
+    QFs,
+    QId,
+    Ratios,
+    Raze,
+    Reval,
+    Save,
+    Set,
+    Sum,
+    Sv,
+    Value,
+    Var,
+    Vs,
+    Wavg,
+    While,
+    Within,
+    Wsum,
+}
+
+impl Builtin {
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"asc" => Some(Self::Asc),
+            b"avg" => Some(Self::Avg),
+            b"bin" => Some(Self::Bin),
+            b"by" => Some(Self::By),
+            b"config" => Some(Self::Config),
+            b"cor" => Some(Self::Cor),
+            b"cov" => Some(Self::Cov),
+            b"desc" => Some(Self::Desc),
+            b"dev" => Some(Self::Dev),
+            b"do" => Some(Self::Do),
+            b"ema" => Some(Self::Ema),
+            b"enlist" => Some(Self::Enlist),
+            b"findall" => Some(Self::FindAll),
+            b"get" => Some(Self::Get),
+            b"if" => Some(Self::If),
+            b"load" => Some(Self::Load),
+            b".Q.fs" => Some(Self::QFs),
+            b".Q.id" => Some(Self::QId),
+            b"ratios" => Some(Self::Ratios),
+            b"raze" => Some(Self::Raze),
+            b"reval" => Some(Self::Reval),
+            b"save" => Some(Self::Save),
+            b"set" => Some(Self::Set),
+            b"sum" => Some(Self::Sum),
+            b"sv" => Some(Self::Sv),
+            b"value" => Some(Self::Value),
+            b"var" => Some(Self::Var),
+            b"vs" => Some(Self::Vs),
+            b"wavg" => Some(Self::Wavg),
+            b"while" => Some(Self::While),
+            b"within" => Some(Self::Within),
+            b"wsum" => Some(Self::Wsum),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Avg => "avg",
+            Self::Bin => "bin",
+            Self::By => "by",
+            Self::Config => "config",
+            Self::Cor => "cor",
+            Self::Cov => "cov",
+            Self::Desc => "desc",
+            Self::Dev => "dev",
+            Self::Do => "do",
+            Self::Ema => "ema",
+            Self::Enlist => "enlist",
+            Self::FindAll => "findall",
+            Self::Get => "get",
+            Self::If => "if",
+            Self::Load => "load",
+            Self::QFs => ".Q.fs",
+            Self::QId => ".Q.id",
+            Self::Ratios => "ratios",
+            Self::Raze => "raze",
+            Self::Reval => "reval",
+            Self::Save => "save",
+            Self::Set => "set",
+            Self::Sum => "sum",
+            Self::Sv => "sv",
+            Self::Value => "value",
+            Self::Var => "var",
+            Self::Vs => "vs",
+            Self::Wavg => "wavg",
+            Self::While => "while",
+            Self::Within => "within",
+            Self::Wsum => "wsum",
+        }
+    }
+
+    // dyadic builtins can be used infix, like verbs
+    pub fn is_infix(&self) -> bool {
+        matches!(
+            self,
+            Self::Bin
+                | Self::Cor
+                | Self::Cov
+                | Self::Ema
+                | Self::FindAll
+                | Self::Set
+                | Self::Sv
+                | Self::Vs
+                | Self::Wavg
+                | Self::Within
+                | Self::Wsum
+        )
+    }
+}
+
+// the items of a list, kept inline while short with the smallvec feature
+#[cfg(not(feature = "smallvec"))]
+pub type List<T> = Vec<T>;
+#[cfg(feature = "smallvec")]
+pub type List<T> = smallvec::SmallVec<[T; 4]>;
+
+// vec! for List
+#[cfg(not(feature = "smallvec"))]
+macro_rules! list {
+    ($($x:tt)*) => { vec![$($x)*] };
+}
+#[cfg(feature = "smallvec")]
+macro_rules! list {
+    ($($x:tt)*) => { smallvec::smallvec![$($x)*] };
+}
+pub(crate) use list;
+
+// a List taking over the buffer of a Vec, or its items if they fit inline
+#[cfg(not(feature = "smallvec"))]
+pub fn to_list<T>(v: Vec<T>) -> List<T> {
+    v
+}
+#[cfg(feature = "smallvec")]
+pub fn to_list<T>(v: Vec<T>) -> List<T> {
+    List::from_vec(v)
+}
+
 #[derive(Clone, Debug)]
 pub enum K0 {
     Nil,
+    Bool(bool),
     Char(u8),
     Int(i64),
     Float(f64),
@@ -58,16 +324,462 @@ pub enum K0 {
 
     Verb(Verb),
     Adverb(Adverb),
+    Builtin(Builtin),
+
+    BoolList(List<bool>),
+    CharList(List<u8>),
+    IntList(List<i64>),
+    FloatList(List<f64>),
+    SymList(List<Sym>),
+    GenList(List<K>),
+
+    // keys and values, lists of the same length
+    Dict(K, K),
+
+    Func(Func),
+    // a verb or function modified by an adverb, like +/
+    Derived(Adverb, K),
+    // a verb or function with some of its arguments fixed, None for the ones still to come
+    Projection(K, Vec<Option<K>>),
+}
 
-    CharList(Vec<u8>),
-    IntList(Vec<i64>),
-    FloatList(Vec<f64>),
-    SymList(Vec<Sym>),
-    GenList(Vec<K>),
+// a lambda, with its source text for display
+#[derive(Clone, Debug)]
+pub struct Func {
+    pub src: Vec<u8>,
+    // explicit parameter names, x y z are implicit otherwise
+    pub params: Option<Vec<Sym>>,
+    pub body: Vec<Option<ASTNode>>,
+}
+
+impl Func {
+    // number of parameters, up to the last of x y z used in the body when implicit
+    pub fn valence(&self) -> usize {
+        match &self.params {
+            Some(params) => params.len(),
+            None => (1..=3)
+                .rev()
+                .find(|&n| {
+                    let name = Sym::new(&b"xyz"[n - 1..n]);
+                    self.body.iter().flatten().any(|ast| ast.uses(name))
+                })
+                .unwrap_or(1),
+        }
+    }
 }
 
 type KResult = Result<K, RuntimeErrorCode>;
 
+// int null and infinity, displayed as 0N and 0W
+pub const NULL_INT: i64 = i64::MIN;
+pub const INF_INT: i64 = i64::MAX;
+
+// how the arguments of an atomic dyadic verb pair up: an atom extends to the length of a
+// list, lists pair item by item
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Atoms,
+    AtomList,
+    ListAtom,
+    Lists,
+}
+
+pub fn conform(a: &K0, b: &K0) -> Result<Shape, RuntimeErrorCode> {
+    match (a.list_len(), b.list_len()) {
+        (None, None) => Ok(Shape::Atoms),
+        (None, Some(_)) => Ok(Shape::AtomList),
+        (Some(_), None) => Ok(Shape::ListAtom),
+        (Some(n), Some(m)) if n == m => Ok(Shape::Lists),
+        _ => Err(RuntimeErrorCode::Length),
+    }
+}
+
+impl K0 {
+    // number of items in a list, None for atoms
+    pub fn list_len(&self) -> Option<usize> {
+        match self {
+            Self::BoolList(x) => Some(x.len()),
+            Self::CharList(x) => Some(x.len()),
+            Self::IntList(x) => Some(x.len()),
+            Self::FloatList(x) => Some(x.len()),
+            Self::SymList(x) => Some(x.len()),
+            Self::GenList(x) => Some(x.len()),
+            _ => None,
+        }
+    }
+
+    // count of items, 1 for atoms
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Dict(keys, _) => keys.len(),
+            _ => self.list_len().unwrap_or(1),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // split a list into its items, None for atoms
+    pub fn items(&self) -> Option<Vec<K>> {
+        match self {
+            Self::BoolList(x) => Some(x.iter().map(|&b| b.into()).collect()),
+            Self::CharList(x) => Some(x.iter().map(|&c| c.into()).collect()),
+            Self::IntList(x) => Some(x.iter().map(|&i| i.into()).collect()),
+            Self::FloatList(x) => Some(x.iter().map(|&f| f.into()).collect()),
+            Self::SymList(x) => Some(x.iter().map(|&s| s.into()).collect()),
+            Self::GenList(x) => Some(x.to_vec()),
+            _ => None,
+        }
+    }
+
+    // items at the given (in range) indices of a list
+    pub fn select(&self, indices: &[usize]) -> K {
+        match self {
+            Self::BoolList(x) => K0::BoolList(indices.iter().map(|&i| x[i]).collect()),
+            Self::CharList(x) => K0::CharList(indices.iter().map(|&i| x[i]).collect()),
+            Self::IntList(x) => K0::IntList(indices.iter().map(|&i| x[i]).collect()),
+            Self::FloatList(x) => K0::FloatList(indices.iter().map(|&i| x[i]).collect()),
+            Self::SymList(x) => K0::SymList(indices.iter().map(|&i| x[i]).collect()),
+            Self::GenList(x) => {
+                return indices
+                    .iter()
+                    .map(|&i| x[i].clone())
+                    .collect::<Vec<_>>()
+                    .into()
+            }
+            _ => K0::GenList(list![]),
+        }
+        .into()
+    }
+
+    // match: same type and shape with equal items, nulls match each other
+    pub fn deep_eq(&self, other: &K0) -> bool {
+        fn float_eq(x: f64, y: f64) -> bool {
+            x == y || (x.is_nan() && y.is_nan())
+        }
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Bool(x), Self::Bool(y)) => x == y,
+            (Self::Char(x), Self::Char(y)) => x == y,
+            (Self::Int(x), Self::Int(y)) => x == y,
+            (Self::Float(x), Self::Float(y)) => float_eq(*x, *y),
+            (Self::Sym(x), Self::Sym(y)) | (Self::Name(x), Self::Name(y)) => x == y,
+            (Self::Verb(x), Self::Verb(y)) => *x as u8 == *y as u8,
+            (Self::Adverb(x), Self::Adverb(y)) => *x as u8 == *y as u8,
+            (Self::Builtin(x), Self::Builtin(y)) => x.name() == y.name(),
+            (Self::BoolList(x), Self::BoolList(y)) => x == y,
+            (Self::CharList(x), Self::CharList(y)) => x == y,
+            (Self::IntList(x), Self::IntList(y)) => x == y,
+            (Self::FloatList(x), Self::FloatList(y)) => {
+                x.len() == y.len() && x.iter().zip(y).all(|(&i, &j)| float_eq(i, j))
+            }
+            (Self::SymList(x), Self::SymList(y)) => x == y,
+            (Self::GenList(x), Self::GenList(y)) => {
+                x.len() == y.len() && x.iter().zip(y).all(|(i, j)| i.deep_eq(j))
+            }
+            (Self::Dict(xk, xv), Self::Dict(yk, yv)) => xk.deep_eq(yk) && xv.deep_eq(yv),
+            (Self::Func(x), Self::Func(y)) => x.src == y.src,
+            (Self::Derived(xa, xf), Self::Derived(ya, yf)) => {
+                *xa as u8 == *ya as u8 && xf.deep_eq(yf)
+            }
+            (Self::Projection(xf, xargs), Self::Projection(yf, yargs)) => {
+                xf.deep_eq(yf)
+                    && xargs.len() == yargs.len()
+                    && xargs.iter().zip(yargs).all(|(i, j)| match (i, j) {
+                        (Some(i), Some(j)) => i.deep_eq(j),
+                        (i, j) => i.is_none() && j.is_none(),
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    // a hash agreeing with deep_eq: items that match hash the same
+    pub fn deep_hash<H: Hasher>(&self, state: &mut H) {
+        fn float_hash<H: Hasher>(x: f64, state: &mut H) {
+            // every null is one NaN, and adding 0.0 turns -0.0 into 0.0
+            let x = if x.is_nan() { f64::NAN } else { x + 0.0 };
+            x.to_bits().hash(state);
+        }
+        mem::discriminant(self).hash(state);
+        match self {
+            Self::Nil => {}
+            Self::Bool(x) => x.hash(state),
+            Self::Char(x) => x.hash(state),
+            Self::Int(x) => x.hash(state),
+            Self::Float(x) => float_hash(*x, state),
+            Self::Sym(x) | Self::Name(x) => x.hash(state),
+            Self::Verb(x) => (*x as u8).hash(state),
+            Self::Adverb(x) => (*x as u8).hash(state),
+            Self::Builtin(x) => x.name().hash(state),
+            Self::BoolList(x) => x[..].hash(state),
+            Self::CharList(x) => x[..].hash(state),
+            Self::IntList(x) => x[..].hash(state),
+            Self::FloatList(x) => {
+                x.len().hash(state);
+                x.iter().for_each(|&f| float_hash(f, state));
+            }
+            Self::SymList(x) => x[..].hash(state),
+            Self::GenList(x) => {
+                x.len().hash(state);
+                x.iter().for_each(|k| k.deep_hash(state));
+            }
+            Self::Dict(k, v) => {
+                k.deep_hash(state);
+                v.deep_hash(state);
+            }
+            Self::Func(x) => x.src.hash(state),
+            Self::Derived(a, f) => {
+                (*a as u8).hash(state);
+                f.deep_hash(state);
+            }
+            Self::Projection(f, args) => {
+                f.deep_hash(state);
+                args.len().hash(state);
+                for arg in args {
+                    arg.is_some().hash(state);
+                    if let Some(arg) = arg {
+                        arg.deep_hash(state);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// make a list of names usable as column names: strip non-alphanumeric characters, prefix
+// names not starting with a letter with an a, suffix reserved words with 1 and deduplicate
+// by suffixing numbers
+pub fn sanitize_names(names: &[Sym]) -> Vec<Sym> {
+    let mut seen = HashSet::with_capacity(names.len());
+    names
+        .iter()
+        .map(|name| {
+            let mut name = name
+                .as_bytes()
+                .iter()
+                .copied()
+                .filter(u8::is_ascii_alphanumeric)
+                .collect::<Vec<_>>();
+            if !matches!(name.first(), Some(c) if c.is_ascii_alphabetic()) {
+                name.insert(0, b'a');
+            }
+            if name == b"nil" || Builtin::from_name(&name).is_some() {
+                name.push(b'1');
+            }
+            let mut unique = name.clone();
+            for n in 1.. {
+                if seen.insert(unique.clone()) {
+                    break;
+                }
+                unique = [&name[..], n.to_string().as_bytes()].concat();
+            }
+            Sym::new(&unique)
+        })
+        .collect()
+}
+
+impl K {
+    // join two values into a single list, atoms are treated as 1 item lists
+    pub fn join(&self, other: &K) -> K {
+        match (self.deref(), other.deref()) {
+            (K0::BoolList(x), K0::BoolList(y)) => [&x[..], y].concat().into(),
+            (K0::CharList(x), K0::CharList(y)) => [&x[..], y].concat().into(),
+            (K0::IntList(x), K0::IntList(y)) => [&x[..], y].concat().into(),
+            (K0::FloatList(x), K0::FloatList(y)) => [&x[..], y].concat().into(),
+            (K0::SymList(x), K0::SymList(y)) => [&x[..], y].concat().into(),
+            (K0::Dict(_, _), K0::Dict(_, _)) => self.merge(other),
+            _ => {
+                let mut items = self.items().unwrap_or_else(|| vec![self.clone()]);
+                items.extend(other.items().unwrap_or_else(|| vec![other.clone()]));
+                items.into()
+            }
+        }
+    }
+
+    // d,e: keys of d in order followed by the new keys of e, with values of e winning
+    fn merge(&self, other: &K) -> K {
+        let (mut keys, mut values) = match self.deref() {
+            K0::Dict(k, v) => (k.items().unwrap_or_default(), v.items().unwrap_or_default()),
+            _ => (vec![], vec![]),
+        };
+        if let K0::Dict(k, v) = other.deref() {
+            for (key, value) in k
+                .items()
+                .unwrap_or_default()
+                .into_iter()
+                .zip(v.items().unwrap_or_default())
+            {
+                match keys.iter().position(|k| k.deep_eq(&key)) {
+                    Some(i) => values[i] = value,
+                    None => {
+                        keys.push(key);
+                        values.push(value);
+                    }
+                }
+            }
+        }
+        K0::Dict(keys.into(), values.into()).into()
+    }
+
+    // x!y: a dictionary of keys x and values y
+    pub fn dict(&self, values: &K) -> KResult {
+        match (self.list_len(), values.list_len()) {
+            (None, _) => Err(RuntimeErrorCode::Type),
+            (Some(x), Some(y)) if x == y => Ok(K0::Dict(self.clone(), values.clone()).into()),
+            _ => Err(RuntimeErrorCode::Length),
+        }
+    }
+
+    // *x: the first item of a list or value of a dictionary, typed null if empty, atoms are their
+    // own first item
+    pub fn first(&self) -> K {
+        match self.deref() {
+            K0::Dict(_, values) => values.item(0),
+            x if x.list_len().is_some() => self.item(0),
+            _ => self.clone(),
+        }
+    }
+
+    // +x: transpose a list of equal length lists, atoms in it going with every row
+    pub fn flip(&self) -> KResult {
+        let rows = match self.deref() {
+            K0::GenList(x) => x,
+            K0::Dict(_, _) => return Err(RuntimeErrorCode::Nyi),
+            _ => return Ok(self.clone()),
+        };
+        let mut lens = rows.iter().filter_map(|r| r.list_len());
+        let n = match lens.next() {
+            Some(n) if lens.all(|m| m == n) => n,
+            Some(_) => return Err(RuntimeErrorCode::Length),
+            None => return Ok(self.clone()),
+        };
+        Ok((0..n as i64)
+            .map(|i| {
+                rows.iter()
+                    .map(|r| match r.list_len() {
+                        Some(_) => r.item(i),
+                        None => r.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into()
+            })
+            .collect::<Vec<K>>()
+            .into())
+    }
+
+    // join the items of a list together, flattening exactly one level
+    pub fn raze(&self) -> K {
+        match self.deref() {
+            K0::GenList(x) => match x.split_first() {
+                Some((first, rest)) => rest.iter().fold(first.clone(), |acc, k| acc.join(k)),
+                None => self.clone(),
+            },
+            K0::BoolList(_)
+            | K0::CharList(_)
+            | K0::IntList(_)
+            | K0::FloatList(_)
+            | K0::SymList(_) => self.clone(),
+            _ => vec![self.clone()].into(),
+        }
+    }
+
+    // typed null shaped like a value: a null atom for atoms, nulls for each item of lists
+    pub fn null(&self) -> K {
+        match self.deref() {
+            K0::Bool(_) => K0::Bool(false).into(),
+            K0::Char(_) => K0::Char(b' ').into(),
+            K0::Int(_) => K0::Int(NULL_INT).into(),
+            K0::Float(_) => K0::Float(f64::NAN).into(),
+            K0::Sym(_) => K0::Sym(Sym::new(b"")).into(),
+            K0::BoolList(x) => K0::BoolList(list![false; x.len()]).into(),
+            K0::CharList(x) => K0::CharList(list![b' '; x.len()]).into(),
+            K0::IntList(x) => K0::IntList(list![NULL_INT; x.len()]).into(),
+            K0::FloatList(x) => K0::FloatList(list![f64::NAN; x.len()]).into(),
+            K0::SymList(x) => K0::SymList(list![Sym::new(b""); x.len()]).into(),
+            K0::GenList(x) => x.iter().map(K::null).collect::<Vec<_>>().into(),
+            _ => K0::Nil.into(),
+        }
+    }
+
+    // item i of a list, or the typed null of the list's items when out of range
+    pub fn item(&self, i: i64) -> K {
+        let item = |list_len| usize::try_from(i).ok().filter(|&i| i < list_len);
+        match self.deref() {
+            K0::BoolList(x) => matches!(item(x.len()), Some(i) if x[i]).into(),
+            K0::CharList(x) => item(x.len()).map_or(b' ', |i| x[i]).into(),
+            K0::IntList(x) => item(x.len()).map_or(NULL_INT, |i| x[i]).into(),
+            K0::FloatList(x) => item(x.len()).map_or(f64::NAN, |i| x[i]).into(),
+            K0::SymList(x) => item(x.len()).map_or_else(|| Sym::new(b""), |i| x[i]).into(),
+            // nulls of a general list take the type of its first item
+            K0::GenList(x) => match item(x.len()) {
+                Some(i) => x[i].clone(),
+                None => x.first().map_or_else(|| K0::Nil.into(), K::null),
+            },
+            _ => self.null(),
+        }
+    }
+
+    // x@i: items of a list at int indices or values of a dictionary at keys, with typed nulls for
+    // indices out of range and missing keys. Lists of indices give lists of items.
+    pub fn index_at(&self, idx: &K) -> KResult {
+        match (self.deref(), idx.deref()) {
+            (K0::Dict(keys, values), _) => {
+                match keys
+                    .items()
+                    .unwrap_or_default()
+                    .iter()
+                    .position(|k| k.deep_eq(idx))
+                {
+                    Some(i) => Ok(values.item(i as i64)),
+                    None => match idx.items() {
+                        Some(idx) => idx
+                            .iter()
+                            .map(|i| self.index_at(i))
+                            .collect::<Result<Vec<_>, _>>()
+                            .map(K::from),
+                        None => Ok(values.item(-1)),
+                    },
+                }
+            }
+            (x, _) if x.list_len().is_none() => Err(RuntimeErrorCode::Type),
+            (_, K0::Bool(_) | K0::BoolList(_)) => self.index_at(&bools_to_ints(idx)),
+            (_, K0::Int(i)) => Ok(self.item(*i)),
+            (_, K0::IntList(i)) if i.is_empty() => Ok(self.select(&[])),
+            (_, K0::IntList(i)) => Ok(i.iter().map(|&i| self.item(i)).collect::<Vec<_>>().into()),
+            (_, K0::GenList(i)) => i
+                .iter()
+                .map(|i| self.index_at(i))
+                .collect::<Result<Vec<_>, _>>()
+                .map(K::from),
+            _ => Err(RuntimeErrorCode::Type),
+        }
+    }
+
+    // index at depth: the first index selects from x like x@i, the next from that and so on, with
+    // a list of indices selecting several items to index into further. Walking past the end of a
+    // list at any depth gives the null of that list's items, and indexing further into an atom
+    // gives the null of the atom's type.
+    pub fn index_path(&self, path: &[K]) -> KResult {
+        let (i, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return Ok(self.clone()),
+        };
+        if self.list_len().is_none() && !matches!(self.deref(), K0::Dict(_, _)) {
+            return Ok(self.null());
+        }
+        match i.items() {
+            Some(is) if !rest.is_empty() => is
+                .into_iter()
+                .map(|i| self.index_path(&[&[i], rest].concat()))
+                .collect::<Result<Vec<_>, _>>()
+                .map(K::from),
+            _ => self.index_at(i)?.index_path(rest),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct K(pub Arc<K0>); // remove pub if print_variable_rcs is deleted
 
@@ -93,10 +805,11 @@ impl Deref for K {
 
 impl fmt::Display for K {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        self.0.fmt(f)
     }
 }
 
+// {:#} prints a matrix one row per line, as the console does
 impl fmt::Display for K0 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fn fmt_list<T: fmt::Display>(
@@ -120,27 +833,153 @@ impl fmt::Display for K0 {
             Ok(())
         }
 
+        fn fmt_int(f: &mut fmt::Formatter<'_>, x: i64) -> fmt::Result {
+            match x {
+                NULL_INT => write!(f, "0N"),
+                INF_INT => write!(f, "0W"),
+                x if x == -INF_INT => write!(f, "-0W"),
+                _ => write!(f, "{}", x),
+            }
+        }
+
+        // a string when the bytes are text the tokenizer reads back the same, 0x and the bytes in
+        // hex otherwise
+        fn fmt_chars(f: &mut fmt::Formatter<'_>, x: &[u8]) -> fmt::Result {
+            match str::from_utf8(x) {
+                Ok(s)
+                    if s.chars()
+                        .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t')) =>
+                {
+                    write!(f, "\"")?;
+                    for c in s.chars() {
+                        match c {
+                            '"' => write!(f, "\\\"")?,
+                            '\\' => write!(f, "\\\\")?,
+                            '\n' => write!(f, "\\n")?,
+                            '\r' => write!(f, "\\r")?,
+                            '\t' => write!(f, "\\t")?,
+                            c => write!(f, "{}", c)?,
+                        }
+                    }
+                    write!(f, "\"")
+                }
+                _ => {
+                    write!(f, "0x")?;
+                    for b in x {
+                        write!(f, "{:02x}", b)?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+
+        // nulls are unsigned and infinities signed, -0n prints as 0n
         fn fmt_float(f: &mut fmt::Formatter<'_>, x: f64) -> fmt::Result {
             match x.classify() {
                 FpCategory::Nan => write!(f, "0n"),
                 FpCategory::Infinite => {
                     write!(f, "{}0w", if x.is_sign_negative() { "-" } else { "" })
                 }
+                // negative zero prints as plain 0
+                FpCategory::Zero => write!(f, "0"),
                 _ => write!(f, "{}", x),
             }
         }
 
+        // formatted items of a list of at least two equal-length numeric lists, one row per list
+        fn matrix_cells(list: &[K]) -> Option<Vec<Vec<String>>> {
+            let width = list.first()?.list_len()?;
+            if list.len() < 2 || width < 2 {
+                return None;
+            }
+            list.iter()
+                .map(|row| match row.deref() {
+                    K0::IntList(x) if x.len() == width => {
+                        Some(x.iter().map(|&i| K0::Int(i).to_string()).collect())
+                    }
+                    K0::FloatList(x) if x.len() == width => {
+                        Some(x.iter().map(|&i| K0::Float(i).to_string()).collect())
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+
+        // right-align the items of each column so the rows read as a grid, on separate lines in
+        // the alternate form
+        fn fmt_matrix(f: &mut fmt::Formatter<'_>, cells: &[Vec<String>]) -> fmt::Result {
+            let widths = (0..cells[0].len())
+                .map(|j| cells.iter().map(|row| row[j].len()).max().unwrap_or(0))
+                .collect::<Vec<_>>();
+            let (open, separator, close) = match f.alternate() {
+                true => ("", "\n", ""),
+                false => ("(", ";", ")"),
+            };
+            write!(f, "{}", open)?;
+            for (i, row) in cells.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "{}", separator)?;
+                }
+                for (j, (cell, width)) in row.iter().zip(&widths).enumerate() {
+                    if j > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{:>width$}", cell, width = width)?;
+                }
+            }
+            write!(f, "{}", close)
+        }
+
+        if self.list_len() == Some(1) {
+            write!(f, ",")?;
+        }
         match self {
             Self::Nil => write!(f, "nil"),
-            Self::Char(x) => write!(f, "{:?}", *x as char),
-            Self::Int(x) => write!(f, "{}", x),
+            Self::Bool(x) => write!(f, "{}b", *x as u8),
+            Self::Char(x) if x.is_ascii_graphic() || matches!(x, b' ' | b'\n' | b'\r' | b'\t') => {
+                write!(f, "{:?}", *x as char)
+            }
+            Self::Char(x) => write!(f, "0x{:02x}", x),
+            Self::Int(x) => fmt_int(f, *x),
             Self::Float(x) => fmt_float(f, *x),
             Self::Sym(x) => write!(f, "{}", x),
             Self::Name(x) => write!(f, "{}", x),
             Self::Verb(x) => write!(f, "{:?}", x),
             Self::Adverb(x) => write!(f, "{:?}", x),
-            Self::CharList(x) => write!(f, "{:?}", String::from_utf8_lossy(x)),
-            Self::IntList(x) => fmt_list(f, x, false, " "),
+            Self::Builtin(x) => write!(f, "{}", x.name()),
+            Self::Func(x) => write!(f, "{}", String::from_utf8_lossy(&x.src)),
+            Self::Derived(a, x) => write!(f, "{}{}", x, a.symbol()),
+            Self::Projection(x, args) => {
+                write!(f, "{}[", x)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";")?;
+                    }
+                    if let Some(arg) = arg {
+                        write!(f, "{}", arg)?;
+                    }
+                }
+                write!(f, "]")
+            }
+            // all the digits followed by a single b, like 101b
+            Self::BoolList(x) if x.is_empty() => Ok(()),
+            Self::BoolList(x) => {
+                for b in x {
+                    write!(f, "{}", *b as u8)?;
+                }
+                write!(f, "b")
+            }
+            Self::CharList(x) => fmt_chars(f, x),
+            Self::IntList(x) => {
+                if let Some((last, rest)) = x.split_last() {
+                    for k in rest {
+                        fmt_int(f, *k)?;
+                        write!(f, " ")?;
+                    }
+                    fmt_int(f, *last)?;
+                }
+                Ok(())
+            }
             Self::FloatList(x) => {
                 if let Some((last, rest)) = x.split_last() {
                     for k in rest {
@@ -152,12 +991,44 @@ impl fmt::Display for K0 {
                 Ok(())
             }
             Self::SymList(x) => fmt_list(f, x, false, ""),
-            Self::GenList(x) => fmt_list(f, x, true, ";"),
+            Self::GenList(x) => match matrix_cells(x) {
+                Some(cells) => fmt_matrix(f, &cells),
+                None => fmt_list(f, x, x.len() != 1, ";"),
+            },
+            Self::Dict(keys, values) if keys.is_empty() => write!(f, "{}!{}", keys, values),
+            // one key| value line per entry, with the keys padded to the same width
+            Self::Dict(keys, values) => {
+                let keys = keys
+                    .items()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|k| match k.deref() {
+                        K0::Sym(s) => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+                        _ => k.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                let width = keys.iter().map(String::len).max().unwrap_or(0);
+                let values = values.items().unwrap_or_default();
+                for (i, (k, v)) in keys.iter().zip(&values).enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{:<width$}| {}", k, v, width = width)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
 macro_rules! impl_from {
+    (Vec<$item: ty>, $kvariant: path) => {
+        impl From<Vec<$item>> for K {
+            fn from(v: Vec<$item>) -> K {
+                $kvariant(to_list(v)).into()
+            }
+        }
+    };
     ($type: ty, $kvariant: path) => {
         impl From<$type> for K {
             fn from(v: $type) -> K {
@@ -167,22 +1038,24 @@ macro_rules! impl_from {
     };
 }
 
+impl_from!(bool, K0::Bool);
 impl_from!(u8, K0::Char);
 impl_from!(i64, K0::Int);
 impl_from!(f64, K0::Float);
 impl_from!(Sym, K0::Sym);
+impl_from!(Vec<bool>, K0::BoolList);
 impl_from!(Vec<u8>, K0::CharList);
 impl_from!(Vec<i64>, K0::IntList);
 impl_from!(Vec<f64>, K0::FloatList);
 impl_from!(Vec<Sym>, K0::SymList);
 
 impl From<Vec<K>> for K {
-    // convert to a [char|int|float|sym]list if the Vec exclusively has those elements
+    // convert to a [bool|char|int|float|sym]list if the Vec exclusively has those elements
     fn from(v: Vec<K>) -> Self {
         if let Some((first, rest)) = v.split_first() {
             if matches!(
                 first.deref(),
-                K0::Char(_) | K0::Int(_) | K0::Float(_) | K0::Sym(_)
+                K0::Bool(_) | K0::Char(_) | K0::Int(_) | K0::Float(_) | K0::Sym(_)
             ) && rest
                 .iter()
                 .all(|x| mem::discriminant(first.deref()) == mem::discriminant(x.deref()))
@@ -200,6 +1073,7 @@ impl From<Vec<K>> for K {
                     };
                 }
                 return match first.deref() {
+                    K0::Bool(_) => to_simple_list!(v, K0::Bool),
                     K0::Char(_) => to_simple_list!(v, K0::Char),
                     K0::Int(_) => to_simple_list!(v, K0::Int),
                     K0::Float(_) => to_simple_list!(v, K0::Float),
@@ -208,6 +1082,246 @@ impl From<Vec<K>> for K {
                 };
             }
         }
-        K0::GenList(v).into()
+        K0::GenList(to_list(v)).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Deref;
+
+    use super::{conform, sanitize_names, Shape, K, K0};
+    use crate::error::RuntimeErrorCode;
+    use crate::sym::Sym;
+
+    #[test]
+    fn conform_test() {
+        let atom = K0::Int(1);
+        let list = K0::IntList(list![1, 2, 3]);
+        let list2 = K0::GenList(list![1.0.into(), 2i64.into(), Sym::new(b"a").into()]);
+        let short = K0::FloatList(list![1.0, 2.0]);
+        assert_eq!(conform(&atom, &K0::Float(2.0)).unwrap(), Shape::Atoms);
+        assert_eq!(conform(&atom, &list).unwrap(), Shape::AtomList);
+        assert_eq!(conform(&list, &atom).unwrap(), Shape::ListAtom);
+        assert_eq!(conform(&list, &list2).unwrap(), Shape::Lists);
+        assert!(matches!(
+            conform(&list, &short),
+            Err(RuntimeErrorCode::Length)
+        ));
+        assert_eq!(
+            conform(&K0::GenList(list![]), &K0::IntList(list![])).unwrap(),
+            Shape::Lists
+        );
+    }
+
+    #[test]
+    fn sanitize_names_test() {
+        let names = [
+            &b"first name"[..],
+            b"price($)",
+            b"2x",
+            b"",
+            b"value",
+            b"price",
+            b"price",
+            b"price1",
+        ]
+        .iter()
+        .map(|x| Sym::new(x))
+        .collect::<Vec<_>>();
+        let expected = [
+            &b"firstname"[..],
+            b"price",
+            b"a2x",
+            b"a",
+            b"value1",
+            b"price1",
+            b"price2",
+            b"price11",
+        ];
+        for (name, expected) in sanitize_names(&names).iter().zip(&expected) {
+            assert_eq!(name.as_bytes(), *expected);
+        }
+    }
+
+    #[test]
+    fn matrix_display_test() {
+        let matrix =
+            |rows: Vec<Vec<i64>>| K0::GenList(rows.into_iter().map(K::from).collect()).to_string();
+        assert_eq!(
+            matrix(vec![vec![1, 2, 3], vec![40, 50, 60]]),
+            "( 1  2  3;40 50 60)"
+        );
+        assert_eq!(
+            matrix(vec![vec![1, -2, 3], vec![-40, 5, 60]]),
+            "(  1 -2  3;-40  5 60)"
+        );
+        // ragged rows and plain vectors are not aligned
+        assert_eq!(matrix(vec![vec![1, 2, 3], vec![40, 50]]), "(1 2 3;40 50)");
+        assert_eq!(K0::IntList(list![1, 20, 300]).to_string(), "1 20 300");
+        // one row per line in the alternate form, anything else as before
+        let lines = |k: K| format!("{:#}", k);
+        let rows = vec![K::from(vec![1i64, 2, 3]), K::from(vec![40i64, 50, 60])];
+        assert_eq!(lines(rows.into()), " 1  2  3\n40 50 60");
+        let floats = vec![K::from(vec![1.5, -2.0]), K::from(vec![10.0, 0.25])];
+        assert_eq!(lines(floats.into()), "1.5   -2\n 10 0.25");
+        let ragged = vec![K::from(vec![1i64, 2, 3]), K::from(vec![40i64, 50])];
+        assert_eq!(lines(ragged.into()), "(1 2 3;40 50)");
+        assert_eq!(lines(7i64.into()), "7");
+    }
+
+    #[test]
+    fn float_display_test() {
+        assert_eq!(K0::Float(-0.0).to_string(), "0");
+        assert_eq!(K0::Float(0.0).to_string(), "0");
+        assert_eq!(K0::Float(f64::INFINITY).to_string(), "0w");
+        assert_eq!(K0::Float(f64::NEG_INFINITY).to_string(), "-0w");
+        assert_eq!(K0::Float(f64::NAN).to_string(), "0n");
+        assert_eq!(K0::Float(-f64::NAN).to_string(), "0n");
+        assert_eq!(
+            K0::FloatList(list![-0.0, 1.5, f64::NEG_INFINITY, -f64::NAN]).to_string(),
+            "0 1.5 -0w 0n"
+        );
+    }
+
+    #[test]
+    fn len_test() {
+        let lists = [
+            (K0::CharList(b"abc"[..].into()), 3),
+            (K0::IntList(list![1, 2]), 2),
+            (K0::FloatList(list![1.0]), 1),
+            (K0::SymList(list![Sym::new(b"a"); 4]), 4),
+            (
+                K0::GenList(list![K0::Nil.into(), K0::IntList(list![1, 2]).into()]),
+                2,
+            ),
+            (K0::GenList(list![]), 0),
+            (K0::IntList(list![]), 0),
+        ];
+        for (list, len) in &lists {
+            assert_eq!(list.len(), *len);
+            assert_eq!(list.items().unwrap().len(), *len);
+        }
+        for atom in [
+            K0::Nil,
+            K0::Char(b'a'),
+            K0::Int(1),
+            K0::Float(1.0),
+            K0::Sym(Sym::new(b"a")),
+        ] {
+            assert_eq!(atom.len(), 1);
+        }
+    }
+
+    #[test]
+    fn dict_test() {
+        let keys: K = vec![Sym::new(b"a"), Sym::new(b"bb"), Sym::new(b"c")].into();
+        let dict = keys.dict(&vec![1i64, 2, 3].into()).unwrap();
+        assert_eq!(dict.len(), 3);
+        assert_eq!(dict.to_string(), "a | 1\nbb| 2\nc | 3");
+        let nested = K::from(vec![1i64, 2]).dict(&vec![dict.clone(), 1.5.into()].into());
+        assert_eq!(
+            nested.unwrap().to_string(),
+            "1| a | 1\nbb| 2\nc | 3\n2| 1.5"
+        );
+        assert!(dict.deep_eq(&keys.dict(&vec![1i64, 2, 3].into()).unwrap()));
+        assert!(!dict.deep_eq(&keys.dict(&vec![1i64, 2, 4].into()).unwrap()));
+        assert!(matches!(
+            keys.dict(&vec![1i64, 2].into()),
+            Err(RuntimeErrorCode::Length)
+        ));
+        assert!(matches!(
+            K::from(1i64).dict(&1i64.into()),
+            Err(RuntimeErrorCode::Type)
+        ));
+        let other = K::from(vec![Sym::new(b"d"), Sym::new(b"a")])
+            .dict(&vec![4.5, 5.5].into())
+            .unwrap();
+        assert_eq!(
+            dict.join(&other).to_string(),
+            "a | 5.5\nbb| 2\nc | 3\nd | 4.5"
+        );
+    }
+
+    #[test]
+    fn in_place_arith_test() {
+        fn buffer(k: &K) -> *const u8 {
+            match k.deref() {
+                K0::IntList(x) => x.as_ptr() as *const u8,
+                K0::FloatList(x) => x.as_ptr() as *const u8,
+                _ => panic!("not a numeric list"),
+            }
+        }
+
+        let x = K::from((0..100_000i64).collect::<Vec<_>>());
+        let ptr = buffer(&x);
+        let x = (x + K::from(1i64)).unwrap();
+        let x = (K::from(2i64) * x).unwrap();
+        assert_eq!(buffer(&x), ptr);
+        assert_eq!(x.item(99_999).to_string(), "200000");
+
+        // a shared list is left alone
+        let shared = x.clone();
+        let y = (x - K::from(1i64)).unwrap();
+        assert_ne!(buffer(&y), ptr);
+        assert_eq!(shared.item(0).to_string(), "2");
+        assert_eq!(y.item(0).to_string(), "1");
+
+        let f = K::from(vec![1.0, 2.0]);
+        let ptr = buffer(&f);
+        let f = (f / K::from(4i64)).unwrap();
+        assert_eq!(buffer(&f), ptr);
+        assert_eq!(f.to_string(), "0.25 0.5");
+
+        // ints divided give floats, so there is nothing to reuse
+        assert_eq!(
+            (K::from(vec![1i64, 2]) / K::from(2i64))
+                .unwrap()
+                .to_string(),
+            "0.5 1"
+        );
+    }
+
+    #[test]
+    fn long_list_arith_test() {
+        // long enough to be split across threads with the parallel feature
+        let n = 250_001;
+        let ints = (0..n as i64).map(|i| i * 7 - 3).collect::<Vec<_>>();
+        let floats = (0..n).map(|i| i as f64 / 3.0).collect::<Vec<_>>();
+        let x = K::from(ints.clone());
+        let y = K::from(floats.clone());
+        match (&x * &x).unwrap().deref() {
+            K0::IntList(v) => assert!(v.iter().zip(&ints).all(|(&r, &i)| r == i * i)),
+            _ => panic!("not an int list"),
+        }
+        match (&x / &y).unwrap().deref() {
+            K0::FloatList(v) => assert!(v
+                .iter()
+                .zip(ints.iter().zip(&floats))
+                .all(|(r, (&i, &j))| r.to_bits() == (i as f64 / j).to_bits())),
+            _ => panic!("not a float list"),
+        }
+        match (&y - &x).unwrap().deref() {
+            K0::FloatList(v) => assert!(v
+                .iter()
+                .zip(floats.iter().zip(&ints))
+                .all(|(r, (&i, &j))| r.to_bits() == (i - j as f64).to_bits())),
+            _ => panic!("not a float list"),
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_zip_map_test() {
+        let x = (0..1001).map(|i| i as f64 / 7.0).collect::<Vec<_>>();
+        let serial = x.iter().map(|i| i * i + 1.0).collect::<Vec<_>>();
+        for threads in 1..9 {
+            let res = super::arith::parallel_zip_map(&x, &x, |i, j| i * j + 1.0, threads);
+            assert_eq!(res.len(), serial.len());
+            assert!(res
+                .iter()
+                .zip(&serial)
+                .all(|(a, b)| a.to_bits() == b.to_bits()));
+        }
     }
 }
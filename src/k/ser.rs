@@ -0,0 +1,252 @@
+use std::convert::TryInto;
+use std::ops::Deref;
+
+use crate::error::RuntimeErrorCode;
+use crate::k::{Builtin, K, K0};
+use crate::parser::{ASTNode, Parser};
+use crate::span::Spanned;
+use crate::sym::Sym;
+use crate::tok::Tokenizer;
+
+// binary encoding of K values: a tag byte followed by the payload, integers little-endian
+
+const NIL: u8 = 0;
+const CHAR: u8 = 1;
+const INT: u8 = 2;
+const FLOAT: u8 = 3;
+const SYM: u8 = 4;
+const NAME: u8 = 5;
+const VERB: u8 = 6;
+const ADVERB: u8 = 7;
+const BUILTIN: u8 = 8;
+const CHAR_LIST: u8 = 9;
+const INT_LIST: u8 = 10;
+const FLOAT_LIST: u8 = 11;
+const SYM_LIST: u8 = 12;
+const GEN_LIST: u8 = 13;
+const DICT: u8 = 14;
+const FUNC: u8 = 15;
+const DERIVED: u8 = 16;
+const BOOL: u8 = 17;
+const BOOL_LIST: u8 = 18;
+const PROJECTION: u8 = 19;
+
+pub fn write_len(buf: &mut Vec<u8>, len: usize) {
+    buf.extend_from_slice(&(len as u64).to_le_bytes());
+}
+
+pub fn write_sym(buf: &mut Vec<u8>, sym: Sym) {
+    let bytes = sym.as_bytes();
+    write_len(buf, bytes.len());
+    buf.extend_from_slice(bytes);
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8], RuntimeErrorCode> {
+    if buf.len() < n {
+        return Err(RuntimeErrorCode::Format);
+    }
+    let (head, rest) = buf.split_at(n);
+    *buf = rest;
+    Ok(head)
+}
+
+fn read_u8(buf: &mut &[u8]) -> Result<u8, RuntimeErrorCode> {
+    Ok(take(buf, 1)?[0])
+}
+
+fn read_8(buf: &mut &[u8]) -> Result<[u8; 8], RuntimeErrorCode> {
+    Ok(take(buf, 8)?.try_into().expect("8 bytes"))
+}
+
+pub fn read_len(buf: &mut &[u8]) -> Result<usize, RuntimeErrorCode> {
+    Ok(u64::from_le_bytes(read_8(buf)?) as usize)
+}
+
+pub fn read_sym(buf: &mut &[u8]) -> Result<Sym, RuntimeErrorCode> {
+    let len = read_len(buf)?;
+    Sym::try_new(take(buf, len)?).ok_or(RuntimeErrorCode::SymbolTableFull)
+}
+
+impl K {
+    pub fn write_to(&self, buf: &mut Vec<u8>) {
+        match self.deref() {
+            K0::Nil => buf.push(NIL),
+            K0::Bool(x) => buf.extend_from_slice(&[BOOL, *x as u8]),
+            K0::Char(x) => buf.extend_from_slice(&[CHAR, *x]),
+            K0::Int(x) => {
+                buf.push(INT);
+                buf.extend_from_slice(&x.to_le_bytes());
+            }
+            K0::Float(x) => {
+                buf.push(FLOAT);
+                buf.extend_from_slice(&x.to_le_bytes());
+            }
+            K0::Sym(x) => {
+                buf.push(SYM);
+                write_sym(buf, *x);
+            }
+            K0::Name(x) => {
+                buf.push(NAME);
+                write_sym(buf, *x);
+            }
+            K0::Verb(x) => buf.extend_from_slice(&[VERB, *x as u8]),
+            K0::Adverb(x) => buf.extend_from_slice(&[ADVERB, *x as u8]),
+            K0::Builtin(x) => {
+                buf.push(BUILTIN);
+                write_sym(buf, Sym::new(x.name().as_bytes()));
+            }
+            K0::BoolList(x) => {
+                buf.push(BOOL_LIST);
+                write_len(buf, x.len());
+                buf.extend(x.iter().map(|&b| b as u8));
+            }
+            K0::CharList(x) => {
+                buf.push(CHAR_LIST);
+                write_len(buf, x.len());
+                buf.extend_from_slice(x);
+            }
+            K0::IntList(x) => {
+                buf.push(INT_LIST);
+                write_len(buf, x.len());
+                x.iter()
+                    .for_each(|i| buf.extend_from_slice(&i.to_le_bytes()));
+            }
+            K0::FloatList(x) => {
+                buf.push(FLOAT_LIST);
+                write_len(buf, x.len());
+                x.iter()
+                    .for_each(|f| buf.extend_from_slice(&f.to_le_bytes()));
+            }
+            K0::SymList(x) => {
+                buf.push(SYM_LIST);
+                write_len(buf, x.len());
+                x.iter().for_each(|&s| write_sym(buf, s));
+            }
+            K0::GenList(x) => {
+                buf.push(GEN_LIST);
+                write_len(buf, x.len());
+                x.iter().for_each(|k| k.write_to(buf));
+            }
+            K0::Dict(keys, values) => {
+                buf.push(DICT);
+                keys.write_to(buf);
+                values.write_to(buf);
+            }
+            // functions are stored as source and parsed again when read
+            K0::Func(x) => {
+                buf.push(FUNC);
+                write_len(buf, x.src.len());
+                buf.extend_from_slice(&x.src);
+            }
+            K0::Derived(a, x) => {
+                buf.push(DERIVED);
+                buf.push(*a as u8);
+                x.write_to(buf);
+            }
+            // each argument is preceded by whether it is there
+            K0::Projection(x, args) => {
+                buf.push(PROJECTION);
+                x.write_to(buf);
+                write_len(buf, args.len());
+                for arg in args {
+                    buf.push(arg.is_some() as u8);
+                    if let Some(arg) = arg {
+                        arg.write_to(buf);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn read_from(buf: &mut &[u8]) -> Result<K, RuntimeErrorCode> {
+        Ok(match read_u8(buf)? {
+            NIL => K0::Nil.into(),
+            CHAR => read_u8(buf)?.into(),
+            INT => i64::from_le_bytes(read_8(buf)?).into(),
+            FLOAT => f64::from_le_bytes(read_8(buf)?).into(),
+            SYM => read_sym(buf)?.into(),
+            NAME => K0::Name(read_sym(buf)?).into(),
+            VERB => K0::Verb(
+                read_u8(buf)?
+                    .try_into()
+                    .map_err(|_| RuntimeErrorCode::Format)?,
+            )
+            .into(),
+            ADVERB => K0::Adverb(
+                read_u8(buf)?
+                    .try_into()
+                    .map_err(|_| RuntimeErrorCode::Format)?,
+            )
+            .into(),
+            BUILTIN => K0::Builtin(
+                Builtin::from_name(read_sym(buf)?.as_bytes()).ok_or(RuntimeErrorCode::Format)?,
+            )
+            .into(),
+            CHAR_LIST => {
+                let len = read_len(buf)?;
+                take(buf, len)?.to_vec().into()
+            }
+            INT_LIST => (0..read_len(buf)?)
+                .map(|_| read_8(buf).map(i64::from_le_bytes))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            FLOAT_LIST => (0..read_len(buf)?)
+                .map(|_| read_8(buf).map(f64::from_le_bytes))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            SYM_LIST => (0..read_len(buf)?)
+                .map(|_| read_sym(buf))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            GEN_LIST => K0::GenList(
+                (0..read_len(buf)?)
+                    .map(|_| K::read_from(buf))
+                    .collect::<Result<_, _>>()?,
+            )
+            .into(),
+            DICT => {
+                let keys = K::read_from(buf)?;
+                keys.dict(&K::read_from(buf)?)
+                    .map_err(|_| RuntimeErrorCode::Format)?
+            }
+            FUNC => {
+                let len = read_len(buf)?;
+                let src = take(buf, len)?;
+                let tokens = Tokenizer::new(src)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| RuntimeErrorCode::Format)?;
+                match Parser::new(src, tokens).parse() {
+                    Ok(Some(ASTNode::Func(Spanned(_, _, func)))) => K0::Func(func).into(),
+                    _ => return Err(RuntimeErrorCode::Format),
+                }
+            }
+            DERIVED => K0::Derived(
+                read_u8(buf)?
+                    .try_into()
+                    .map_err(|_| RuntimeErrorCode::Format)?,
+                K::read_from(buf)?,
+            )
+            .into(),
+            PROJECTION => {
+                let f = K::read_from(buf)?;
+                let args = (0..read_len(buf)?)
+                    .map(|_| match read_u8(buf)? {
+                        0 => Ok(None),
+                        _ => K::read_from(buf).map(Some),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                K0::Projection(f, args).into()
+            }
+            BOOL => (read_u8(buf)? != 0).into(),
+            BOOL_LIST => {
+                let len = read_len(buf)?;
+                take(buf, len)?
+                    .iter()
+                    .map(|&b| b != 0)
+                    .collect::<Vec<_>>()
+                    .into()
+            }
+            _ => return Err(RuntimeErrorCode::Format),
+        })
+    }
+}
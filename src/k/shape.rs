@@ -0,0 +1,111 @@
+use std::ops::Deref;
+
+use crate::error::RuntimeErrorCode;
+use crate::k::{list, KResult, K, K0, NULL_INT};
+
+impl K {
+    // atoms as one item lists
+    fn as_list(&self) -> K {
+        match self.list_len() {
+            Some(_) => self.clone(),
+            None => vec![self.clone()].into(),
+        }
+    }
+
+    // n items of a list from the item at start on, going round to the front again, typed nulls
+    // for an empty list
+    fn cycle(&self, start: usize, n: usize) -> K {
+        match self.list_len() {
+            Some(0) | None => (0..n).map(|_| self.item(0)).collect::<Vec<_>>().into(),
+            Some(len) => self.select(&(start..start + n).map(|i| i % len).collect::<Vec<_>>()),
+        }
+    }
+
+    // n#x: the first n items of x, or the last -n for a negative n, repeating x as needed. An atom
+    // is a one item list.
+    pub fn take(&self, n: i64) -> KResult {
+        if let K0::Dict(keys, values) = self.deref() {
+            return Ok(K0::Dict(keys.take(n)?, values.take(n)?).into());
+        }
+        let list = self.as_list();
+        let (len, m) = (list.len(), n.unsigned_abs() as usize);
+        let start = match n < 0 && len > 0 {
+            true => (len - m % len) % len,
+            false => 0,
+        };
+        Ok(list.cycle(start, m))
+    }
+
+    // i#x: x cycled into nested lists with the dimensions i, a single 0N among them being whatever
+    // fits the count of x exactly
+    pub fn reshape(&self, dims: &[i64]) -> KResult {
+        let list = self.as_list();
+        let dims = dims
+            .iter()
+            .map(|&d| match d {
+                NULL_INT => Ok(None),
+                d if d < 0 => Err(RuntimeErrorCode::Length),
+                d => Ok(Some(d as usize)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let known = dims.iter().flatten().product::<usize>();
+        let dims = match dims.iter().filter(|d| d.is_none()).count() {
+            0 => dims.into_iter().flatten().collect::<Vec<_>>(),
+            1 if known > 0 && list.len() / known * known == list.len() => dims
+                .iter()
+                .map(|d| d.unwrap_or(list.len() / known))
+                .collect(),
+            1 => return Err(RuntimeErrorCode::Length),
+            _ => return Err(RuntimeErrorCode::Domain),
+        };
+        fn fill(list: &K, dims: &[usize], offset: &mut usize) -> K {
+            match dims.split_first() {
+                Some((&d, [])) => {
+                    *offset += d;
+                    list.cycle(*offset - d, d)
+                }
+                Some((&d, rest)) => {
+                    K0::GenList((0..d).map(|_| fill(list, rest, offset)).collect()).into()
+                }
+                None => list.clone(),
+            }
+        }
+        Ok(fill(&list, &dims, &mut 0))
+    }
+
+    // n_x: x without its first n items, or without its last -n items for a negative n
+    pub fn drop(&self, n: i64) -> KResult {
+        if let K0::Dict(keys, values) = self.deref() {
+            return Ok(K0::Dict(keys.drop(n)?, values.drop(n)?).into());
+        }
+        let len = self.list_len().ok_or(RuntimeErrorCode::Type)?;
+        let m = (n.unsigned_abs() as usize).min(len);
+        let range = match n < 0 {
+            true => 0..len - m,
+            false => m..len,
+        };
+        Ok(self.select(&range.collect::<Vec<_>>()))
+    }
+
+    // i_x: x cut into the pieces starting at each of the ascending indices i
+    pub fn cut(&self, indices: &[i64]) -> KResult {
+        let len = self.list_len().ok_or(RuntimeErrorCode::Type)?;
+        if indices.iter().any(|&i| i < 0 || i as usize > len)
+            || indices.windows(2).any(|w| w[0] > w[1])
+        {
+            return Err(RuntimeErrorCode::Index);
+        }
+        if indices.is_empty() {
+            return Ok(K0::GenList(list![self.clone()]).into());
+        }
+        let ends = indices.iter().skip(1).map(|&i| i as usize).chain([len]);
+        Ok(K0::GenList(
+            indices
+                .iter()
+                .zip(ends)
+                .map(|(&start, end)| self.select(&(start as usize..end).collect::<Vec<_>>()))
+                .collect(),
+        )
+        .into())
+    }
+}
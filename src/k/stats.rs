@@ -0,0 +1,160 @@
+use std::ops::Deref;
+
+use crate::error::RuntimeErrorCode;
+use crate::k::arith::{add_int, bools_to_ints, type_error};
+use crate::k::{conform, KResult, Shape, K, K0, NULL_INT};
+
+// numbers of an atom or list as floats, with nulls as NaN
+fn numbers(k: &K) -> Result<Vec<f64>, RuntimeErrorCode> {
+    let int = |i: i64| if i == NULL_INT { f64::NAN } else { i as f64 };
+    Ok(match k.deref() {
+        K0::Bool(b) => vec![*b as u8 as f64],
+        K0::BoolList(x) => x.iter().map(|&b| b as u8 as f64).collect(),
+        K0::Int(i) => vec![int(*i)],
+        K0::Float(f) => vec![*f],
+        K0::IntList(x) => x.iter().map(|&i| int(i)).collect(),
+        K0::FloatList(x) => x.to_vec(),
+        K0::GenList(x) => x
+            .iter()
+            .map(|i| match i.deref() {
+                K0::Bool(b) => Ok(*b as u8 as f64),
+                K0::Int(i) => Ok(int(*i)),
+                K0::Float(f) => Ok(*f),
+                _ => Err(type_error(&[i])),
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err(type_error(&[k])),
+    })
+}
+
+// non-null numbers of an atom or list
+fn floats(k: &K) -> Result<Vec<f64>, RuntimeErrorCode> {
+    Ok(numbers(k)?.into_iter().filter(|f| !f.is_nan()).collect())
+}
+
+// numbers of x and y paired up, an atom going with every item of a list, skipping pairs with a
+// null
+fn paired(x: &K, y: &K) -> Result<Vec<(f64, f64)>, RuntimeErrorCode> {
+    let (xs, ys) = (numbers(x)?, numbers(y)?);
+    let pairs = match conform(x, y)? {
+        Shape::Atoms | Shape::Lists => xs.into_iter().zip(ys).collect::<Vec<_>>(),
+        Shape::AtomList => ys.into_iter().map(|y| (xs[0], y)).collect(),
+        Shape::ListAtom => xs.into_iter().map(|x| (x, ys[0])).collect(),
+    };
+    Ok(pairs
+        .into_iter()
+        .filter(|(x, y)| !x.is_nan() && !y.is_nan())
+        .collect())
+}
+
+fn covariance(pairs: &[(f64, f64)]) -> f64 {
+    let (xs, ys): (Vec<_>, Vec<_>) = pairs.iter().copied().unzip();
+    let (mx, my) = (mean(&xs), mean(&ys));
+    mean(
+        &pairs
+            .iter()
+            .map(|(x, y)| (x - mx) * (y - my))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn mean(x: &[f64]) -> f64 {
+    match x.len() {
+        0 => f64::NAN,
+        n => x.iter().sum::<f64>() / n as f64,
+    }
+}
+
+fn variance(x: &[f64]) -> f64 {
+    let m = mean(x);
+    mean(&x.iter().map(|f| (f - m) * (f - m)).collect::<Vec<_>>())
+}
+
+impl K {
+    // sum of the non-null items, ints stay ints
+    pub fn sum(&self) -> KResult {
+        match self.deref() {
+            K0::Bool(_) | K0::BoolList(_) => bools_to_ints(self).sum(),
+            K0::Int(_) | K0::Float(_) => Ok(self.clone()),
+            K0::IntList(x) => Ok(x
+                .iter()
+                .filter(|&&i| i != NULL_INT)
+                .fold(0i64, |acc, &i| add_int(acc, i))
+                .into()),
+            K0::FloatList(_) | K0::GenList(_) => Ok(floats(self)?.iter().sum::<f64>().into()),
+            _ => Err(type_error(&[self])),
+        }
+    }
+
+    // mean, variance and standard deviation of the non-null items, 0n if there are none
+    pub fn avg(&self) -> KResult {
+        Ok(mean(&floats(self)?).into())
+    }
+
+    pub fn var(&self) -> KResult {
+        Ok(variance(&floats(self)?).into())
+    }
+
+    pub fn dev(&self) -> KResult {
+        Ok(variance(&floats(self)?).sqrt().into())
+    }
+
+    // w wsum x: sum of x weighted by w, and w wavg x divides that by the sum of the weights
+    pub fn wsum(&self, values: &K) -> KResult {
+        Ok(paired(self, values)?
+            .iter()
+            .map(|(w, x)| w * x)
+            .sum::<f64>()
+            .into())
+    }
+
+    pub fn wavg(&self, values: &K) -> KResult {
+        let pairs = paired(self, values)?;
+        let total = pairs.iter().map(|(w, _)| w).sum::<f64>();
+        Ok(if total == 0.0 {
+            f64::NAN
+        } else {
+            pairs.iter().map(|(w, x)| w * x).sum::<f64>() / total
+        }
+        .into())
+    }
+
+    // x cov y and x cor y over the pairs without nulls, 0n correlation if either doesn't vary
+    pub fn cov(&self, rhs: &K) -> KResult {
+        Ok(covariance(&paired(self, rhs)?).into())
+    }
+
+    pub fn cor(&self, rhs: &K) -> KResult {
+        let pairs = paired(self, rhs)?;
+        let (xs, ys): (Vec<_>, Vec<_>) = pairs.iter().copied().unzip();
+        // a constant side has no spread and zero covariance, giving 0/0
+        Ok((covariance(&pairs) / (variance(&xs) * variance(&ys)).sqrt()).into())
+    }
+
+    // a ema x: exponential moving average of x with smoothing factor a, starting from the first
+    // item
+    pub fn ema(&self, values: &K) -> KResult {
+        let alpha = match self.deref() {
+            K0::Int(i) => *i as f64,
+            K0::Float(f) => *f,
+            _ => return Err(type_error(&[self])),
+        };
+        if !(alpha > 0.0 && alpha < 1.0) {
+            return Err(RuntimeErrorCode::Domain);
+        }
+        let xs = numbers(values)?;
+        let mut acc = None;
+        let res = xs
+            .iter()
+            .map(|&x| {
+                let e = acc.map_or(x, |prev| alpha * x + (1.0 - alpha) * prev);
+                acc = Some(e);
+                e
+            })
+            .collect::<Vec<_>>();
+        Ok(match values.list_len() {
+            Some(_) => res.into(),
+            None => xs[0].into(),
+        })
+    }
+}
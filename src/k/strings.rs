@@ -0,0 +1,83 @@
+use std::ops::Deref;
+
+use crate::error::RuntimeErrorCode;
+use crate::k::{to_list, KResult, K, K0};
+use crate::sym::Sym;
+
+// a char or char list as a byte string
+fn as_string(k: &K0) -> Option<&[u8]> {
+    match k {
+        K0::Char(c) => Some(std::slice::from_ref(c)),
+        K0::CharList(x) => Some(x),
+        _ => None,
+    }
+}
+
+impl K {
+    // join a list of strings with a separator
+    pub fn sv(&self, strings: &K) -> KResult {
+        let sep = as_string(self).ok_or(RuntimeErrorCode::Type)?;
+        let parts = match strings.deref() {
+            K0::CharList(x) => x.chunks(1).collect(),
+            K0::GenList(x) => x
+                .iter()
+                .map(|k| as_string(k))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(RuntimeErrorCode::Type)?,
+            _ => return Err(RuntimeErrorCode::Type),
+        };
+        Ok(K0::CharList(to_list(parts.join(sep))).into())
+    }
+
+    // $x: the text of an atom as it displays, symbols without the backtick and bools without the
+    // b, item by item for lists and the values of dictionaries
+    pub fn string(&self) -> K {
+        match self.deref() {
+            K0::Sym(s) => s.as_bytes().to_vec().into(),
+            K0::Bool(b) => vec![b'0' + *b as u8].into(),
+            K0::Char(c) => vec![*c].into(),
+            K0::Dict(keys, values) => K0::Dict(keys.clone(), values.string()).into(),
+            x if x.list_len().is_some() => K0::GenList(
+                self.items()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(K::string)
+                    .collect(),
+            )
+            .into(),
+            _ => self.to_string().into_bytes().into(),
+        }
+    }
+
+    // `$x: the symbol spelled by a string, any bytes allowed, or the symbols of a list of strings
+    pub fn to_sym(&self) -> KResult {
+        match (as_string(self), self.deref()) {
+            (Some(s), _) => Sym::try_new(s)
+                .map(K::from)
+                .ok_or(RuntimeErrorCode::SymbolTableFull),
+            (None, K0::GenList(x)) => x
+                .iter()
+                .map(K::to_sym)
+                .collect::<Result<Vec<_>, _>>()
+                .map(K::from),
+            _ => Err(RuntimeErrorCode::Type),
+        }
+    }
+
+    // split a string on a separator
+    pub fn vs(&self, string: &K) -> KResult {
+        let sep = as_string(self).ok_or(RuntimeErrorCode::Type)?;
+        let string = as_string(string).ok_or(RuntimeErrorCode::Type)?;
+        if sep.is_empty() {
+            return Err(RuntimeErrorCode::Length);
+        }
+        let mut parts = Vec::new();
+        let mut rest = string;
+        while let Some(i) = rest.windows(sep.len()).position(|x| x == sep) {
+            parts.push(K0::CharList(rest[..i].into()).into());
+            rest = &rest[i + sep.len()..];
+        }
+        parts.push(K0::CharList(rest.into()).into());
+        Ok(K0::GenList(to_list(parts)).into())
+    }
+}
@@ -0,0 +1,38 @@
+#![feature(once_cell)]
+
+use crate::error::{EvalError, KError};
+use crate::k::{K, K0};
+use crate::parser::Parser;
+use crate::tok::Tokenizer;
+
+pub mod environ;
+pub mod error;
+mod interpreter;
+pub mod k;
+mod parser;
+mod span;
+pub mod sym;
+pub mod tok;
+pub mod util;
+
+// tokenize, parse and interpret source text, nil if there is nothing to evaluate
+pub fn eval(src: &[u8]) -> Result<K, EvalError> {
+    let tokens = Tokenizer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(EvalError::Lexer)?;
+    match Parser::new(src, tokens).parse() {
+        Ok(Some(ast)) => ast.interpret().map_err(EvalError::Runtime),
+        Ok(None) => Ok(K0::Nil.into()),
+        Err(e) => Err(EvalError::Parser(e)),
+    }
+}
+
+impl EvalError {
+    pub fn location(&self) -> usize {
+        match self {
+            Self::Lexer(KError { location, .. }) => *location,
+            Self::Parser(KError { location, .. }) => *location,
+            Self::Runtime(KError { location, .. }) => *location,
+        }
+    }
+}
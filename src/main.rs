@@ -1,28 +1,19 @@
-#![feature(once_cell)]
-
 use std::env;
-use std::ffi::OsString;
+use std::ffi::OsStr;
 use std::fmt::Debug;
 use std::fs;
 use std::io;
-use std::io::{BufRead, Write};
+use std::io::{BufRead, IsTerminal, Read, Write};
 use std::process;
+use std::str;
+use std::thread;
+use std::time::Instant;
 
-use crate::environ::print_variable_rcs;
-use crate::error::KError;
-use crate::parser::Parser;
-use crate::tok::Tokenizer;
-use crate::util::TrimEnd;
-
-mod environ;
-mod error;
-mod interpreter;
-mod k;
-mod parser;
-mod span;
-mod sym;
-mod tok;
-mod util;
+use ak::environ::{get_context, global_names, print_variable_rcs, set_context};
+use ak::error::{EvalError, KError};
+use ak::sym::Sym;
+use ak::tok::{is_incomplete, Tokenizer};
+use ak::util::{TrimEnd, TrimStart};
 
 fn print_banner() {
     println!(
@@ -33,91 +24,224 @@ fn print_banner() {
     );
 }
 
+// the banner is only for a person at a terminal, not for pipes in or out
+fn at_terminal() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
 fn print_prompt() -> io::Result<()> {
     print!(" ");
     io::stdout().flush()
 }
 
+// a caret under the error location, and ~ under the rest of the offending source if known
 fn print_error<T: Debug>(src: &[u8], error: KError<T>) {
-    println!(
-        "{:?}\n    {}\n    {}^",
-        error.code,
+    let width = error
+        .end
+        .map_or(1, |end| end.saturating_sub(error.location).max(1));
+    eprintln!(
+        "{}\n    {}\n    {}^{}",
+        error.message(),
         String::from_utf8_lossy(src),
-        " ".repeat(error.location)
+        " ".repeat(error.location),
+        "~".repeat(width - 1)
     );
 }
 
-fn run(src: &[u8]) {
-    match Tokenizer::new(src).collect::<Result<Vec<_>, _>>() {
-        Ok(tokens) => {
-            //for token in &tokens {
-            //    //println!("({}, {:?}, {})", token.0, token.1, token.2);
-            //    println!("{:?}", token);
-            //}
-            if tokens.is_empty() {
-                return;
-            }
-            match Parser::new(tokens).parse() {
-                Ok(Some(ast)) => {
-                    //println!("{}", ast);
-                    match ast.interpret() {
-                        Ok(k) => println!("{}", k),
-                        Err(e) => {
-                            print!("runtime error: ");
-                            print_error(src, e);
-                        }
-                    }
-                }
-                Ok(None) => println!("empty!!!"),
-                Err(e) => {
-                    print!("parsing error: ");
-                    print_error(src, e);
-                }
-            }
-        }
+// false if evaluation failed
+fn run(src: &[u8]) -> bool {
+    // nothing to print for blank lines and comments
+    if Tokenizer::new(src).next().is_none() {
+        return true;
+    }
+    match ak::eval(src) {
+        Ok(k) => println!("{:#}", k),
         Err(e) => {
-            print!("tokenizer error: ");
-            print_error(src, e);
+            print_eval_error(src, e);
+            return false;
         }
     }
     //print_variable_rcs();
+    true
+}
+
+fn print_eval_error(src: &[u8], error: EvalError) {
+    match error {
+        EvalError::Lexer(e) => {
+            eprint!("tokenizer error: ");
+            print_error(src, e);
+        }
+        EvalError::Parser(e) => {
+            eprint!("parsing error: ");
+            print_error(src, e);
+        }
+        EvalError::Runtime(e) => {
+            eprint!("runtime error: ");
+            print_error(src, e);
+        }
+    }
+}
+
+// \t[:n] expr - print the milliseconds taken to evaluate expr n times (once by default)
+fn time_expression(count: &[u8], src: &[u8]) {
+    let n = match count {
+        b"" => 1,
+        _ => match str::from_utf8(count)
+            .ok()
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            Some(n) => n,
+            None => return eprintln!("invalid count: {}", String::from_utf8_lossy(count)),
+        },
+    };
+    let start = Instant::now();
+    for _ in 0..n {
+        if let Err(e) = ak::eval(src) {
+            return print_eval_error(src, e);
+        }
+    }
+    println!("{}", start.elapsed().as_millis());
+}
+
+// \d [.ctx] - print or change the current context
+fn change_context(arg: &[u8]) {
+    match arg {
+        b"" => match get_context() {
+            Some(context) => println!("{}", context),
+            None => println!("`."),
+        },
+        b"." => set_context(None),
+        _ if arg.len() > 1
+            && arg[0] == b'.'
+            && arg[1].is_ascii_alphabetic()
+            && arg.iter().all(|x| x.is_ascii_alphanumeric() || *x == b'.') =>
+        {
+            match Sym::try_new(arg) {
+                Some(context) => set_context(Some(context)),
+                None => eprintln!("symbol table full"),
+            }
+        }
+        _ => println!("invalid context: {}", String::from_utf8_lossy(arg)),
+    }
 }
 
-fn run_prompt() -> io::Result<()> {
-    print_prompt()?;
+fn run_command(cmd: &[u8]) {
+    let (name, arg) = cmd.split_at(
+        cmd.iter()
+            .position(|x| x.is_ascii_whitespace())
+            .unwrap_or(cmd.len()),
+    );
+    match name {
+        b"d" => change_context(arg.trim_start()),
+        b"v" => global_names().iter().for_each(|name| println!("{}", name)),
+        b"l" => load_script(arg.trim_start()),
+        b"t" => time_expression(b"", arg.trim_start()),
+        _ if name.starts_with(b"t:") => time_expression(&name[2..], arg.trim_start()),
+        _ => println!("unknown command: \\{}", String::from_utf8_lossy(name)),
+    }
+}
+
+// without a terminal on stdin there is nobody to prompt, so only results are printed
+fn run_prompt(interactive: bool) -> io::Result<()> {
+    if interactive {
+        print_prompt()?;
+    }
     let stdin = io::stdin();
     let mut buf = Vec::new();
+    // where the line being read starts, past any unfinished lines before it
+    let mut start = 0;
     while stdin.lock().read_until(b'\n', &mut buf)? > 0 {
-        let line = buf.trim_end();
-        if !line.is_empty() {
-            if line == br"\\" {
-                process::exit(0);
-            } else {
-                run(line);
-            }
+        let src = buf.trim_end();
+        if start == 0 && src == br"\\" {
+            process::exit(0);
+        } else if start == 0 && src.starts_with(b"\\") {
+            run_command(&src[1..]);
+        } else if start > 0 && (&buf[start..]).trim_end() == b"\\" {
+            // a lone \ drops the unfinished lines
+        } else if is_incomplete(src) {
+            // keep reading lines until brackets and strings are closed
+            start = buf.len();
+            continue;
+        } else if !src.is_empty() {
+            run(src);
         }
+        start = 0;
         buf.clear();
-        print_prompt()?;
+        if interactive {
+            print_prompt()?;
+        }
+    }
+    if interactive {
+        println!();
     }
-    println!();
     Ok(())
 }
 
-fn run_file(fname: OsString) -> io::Result<()> {
-    run(&fs::read(fname)?);
+fn run_file(fname: &OsStr) -> io::Result<()> {
+    run(fs::read(fname)?.trim_end());
     Ok(())
 }
 
+// \l file - run a script against the current globals
+fn load_script(arg: &[u8]) {
+    let fname = String::from_utf8_lossy(arg);
+    if let Err(e) = run_file(OsStr::new(fname.as_ref())) {
+        println!("{}: {}", fname, e);
+    }
+}
+
+// the program from stdin, for pipes
+fn run_stdin() -> io::Result<bool> {
+    let mut src = Vec::new();
+    io::stdin().read_to_end(&mut src)?;
+    Ok(run(src.trim_end()))
+}
+
+// room for the deepest recursion the interpreter allows, even in a debug build
+const STACK_SIZE: usize = 256 << 20;
+
 fn main() -> io::Result<()> {
-    print_banner();
-    let mut args = env::args_os();
-    if args.len() > 2 {
-        eprintln!("usage: {} [script]", env!("CARGO_BIN_NAME"));
-        process::exit(64)
-    } else {
-        match args.nth(1) {
-            Some(arg) => run_file(arg)?,
-            None => run_prompt()?,
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(start)?
+        .join()
+        .unwrap_or_else(|_| process::exit(101))
+}
+
+fn start() -> io::Result<()> {
+    let args = env::args_os().skip(1).collect::<Vec<_>>();
+    // no banner when the output is likely to be piped
+    match args
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()[..]
+    {
+        // a failed one-liner exits non-zero, for the shell
+        [ref flag, ref expr] if flag == "-e" => {
+            if !run(expr.as_bytes()) {
+                process::exit(1)
+            }
+        }
+        [ref flag] if flag == "-" => {
+            if !run_stdin()? {
+                process::exit(1)
+            }
+        }
+        [ref script] if !script.starts_with('-') => {
+            if at_terminal() {
+                print_banner();
+            }
+            run_file(&args[0])?
+        }
+        [] => {
+            if at_terminal() {
+                print_banner();
+            }
+            run_prompt(io::stdin().is_terminal())?
+        }
+        _ => {
+            eprintln!("usage: {} [script | - | -e expr]", env!("CARGO_BIN_NAME"));
+            process::exit(64)
         }
     }
     Ok(())
@@ -1,10 +1,12 @@
 use std::fmt;
 use std::iter::Peekable;
+use std::ops::Deref;
 use std::vec::IntoIter;
 
 use crate::error::{ParserError, ParserErrorCode};
-use crate::k::{Verb, K, K0};
+use crate::k::{list, to_list, Adverb, Builtin, Func, Verb, K, K0};
 use crate::span::Spanned;
+use crate::sym::Sym;
 use crate::tok::Token;
 
 #[derive(Clone, Debug)]
@@ -12,6 +14,7 @@ pub enum ASTNode {
     Expr(Spanned<K>),
     Apply(Spanned<(Box<ASTNode>, Vec<Option<ASTNode>>)>),
     ExprList(Spanned<Vec<Option<ASTNode>>>),
+    Func(Spanned<Func>),
 }
 
 impl fmt::Display for ASTNode {
@@ -41,6 +44,11 @@ impl fmt::Display for ASTNode {
                 write_list(f, list)?;
                 write!(f, "]")
             }
+            Self::Func(Spanned(_, _, func)) => {
+                write!(f, "Func[")?;
+                write_list(f, &func.body)?;
+                write!(f, "]")
+            }
         }
     }
 }
@@ -51,6 +59,7 @@ impl ASTNode {
             Self::Expr(Spanned(s, _, _)) => *s,
             Self::Apply(Spanned(s, _, _)) => *s,
             Self::ExprList(Spanned(s, _, _)) => *s,
+            Self::Func(Spanned(s, _, _)) => *s,
         }
     }
 
@@ -59,11 +68,25 @@ impl ASTNode {
             Self::Expr(Spanned(_, e, _)) => *e,
             Self::Apply(Spanned(_, e, _)) => *e,
             Self::ExprList(Spanned(_, e, _)) => *e,
+            Self::Func(Spanned(_, e, _)) => *e,
+        }
+    }
+
+    // whether a name is referenced, not counting nested functions which have their own scope
+    pub fn uses(&self, name: Sym) -> bool {
+        match self {
+            Self::Expr(Spanned(_, _, k)) => matches!(k.deref(), K0::Name(n) if *n == name),
+            Self::Apply(Spanned(_, _, (f, args))) => {
+                f.uses(name) || args.iter().flatten().any(|ast| ast.uses(name))
+            }
+            Self::ExprList(Spanned(_, _, list)) => list.iter().flatten().any(|ast| ast.uses(name)),
+            Self::Func(_) => false,
         }
     }
 }
 
-pub struct Parser {
+pub struct Parser<'a> {
+    src: &'a [u8],
     tokens_iter: Peekable<IntoIter<Spanned<Token>>>,
 }
 
@@ -78,9 +101,10 @@ macro_rules! extract_ast {
 
 type PResult = Result<Option<ASTNode>, ParserError>;
 
-impl Parser {
-    pub fn new(tokens: Vec<Spanned<Token>>) -> Self {
+impl<'a> Parser<'a> {
+    pub fn new(src: &'a [u8], tokens: Vec<Spanned<Token>>) -> Self {
         Parser {
+            src,
             tokens_iter: tokens.into_iter().peekable(),
         }
     }
@@ -94,6 +118,8 @@ impl Parser {
         if let Some(Spanned(s, _, _)) = self.tokens_iter.next() {
             return Err(ParserError {
                 location: s,
+                end: None,
+                verb: None,
                 code: ParserErrorCode::UnexpectedToken,
             });
         }
@@ -105,66 +131,185 @@ impl Parser {
 
     // infix verb or simple subexpression
     fn expr(&mut self) -> PResult {
+        let noun = self.noun_next();
         let e1 = extract_ast!(self.subexpr());
-        let res = match self
+        // a bare verb on the left of . is the function it applies, like (+) . 2 3
+        let noun = noun
+            || matches!(
+                self.tokens_iter.peek(),
+                Some(Spanned(_, _, Token::Verb(Verb::Dot)))
+            );
+        self.rest(e1, noun).map(Some)
+    }
+
+    // a bare verb followed by another verb is applied monadically, (+) is a noun
+    fn noun_next(&mut self) -> bool {
+        !matches!(
+            self.tokens_iter.peek(),
+            Some(Spanned(
+                _,
+                _,
+                Token::Verb(_) | Token::MonadicVerb(_) | Token::Builtin(_)
+            ))
+        )
+    }
+
+    // everything on the right of the first term of an expression
+    fn rest(&mut self, mut e1: ASTNode, mut noun: bool) -> Result<ASTNode, ParserError> {
+        // brackets apply everything on their left to their contents giving a noun, and an
+        // adverb modifies everything on its left into a verb
+        while let Some(Spanned(s, end, t)) = self
             .tokens_iter
-            .next_if(|x| matches!(x.2, Token::Verb(_) | Token::LtBracket))
+            .next_if(|x| matches!(x.2, Token::LtBracket | Token::Adverb(_)))
         {
-            Some(Spanned(s, e, Token::Verb(v))) => {
-                let verb = ASTNode::Expr(Spanned(s, e, K0::Verb(v).into()));
-                match self.expr()? {
-                    Some(e2) => ASTNode::Apply(Spanned(
-                        e1.start(),
-                        e2.end(),
-                        (Box::new(verb), vec![Some(e1), Some(e2)]),
-                    )),
-                    None => ASTNode::Apply(Spanned(
+            e1 = match t {
+                Token::Adverb(a) => {
+                    noun = false;
+                    self.derive(e1, Spanned(s, end, a))
+                }
+                _ => {
+                    noun = true;
+                    self.brackets(e1, s)?
+                }
+            };
+        }
+        Ok(
+            match self.tokens_iter.next_if(|x| match x.2 {
+                Token::Verb(_) | Token::ModAssign(_) => noun,
+                Token::Builtin(b) => noun && b.is_infix(),
+                _ => false,
+            }) {
+                Some(Spanned(s, e, Token::Verb(v))) => {
+                    let verb = self.adverbs(ASTNode::Expr(Spanned(s, e, K0::Verb(v).into())));
+                    self.infix(e1, verb)?
+                }
+                // x op: y is an assignment with the verb as its middle argument
+                Some(Spanned(s, e, Token::ModAssign(v))) => {
+                    let verb = ASTNode::Expr(Spanned(s, e, K0::Verb(v).into()));
+                    let colon = ASTNode::Expr(Spanned(s, e, K0::Verb(Verb::Colon).into()));
+                    let rhs = self.expr()?;
+                    let end = rhs.as_ref().map_or(e, ASTNode::end);
+                    ASTNode::Apply(Spanned(
                         e1.start(),
-                        verb.end(),
-                        (Box::new(verb), vec![Some(e1), None]),
-                    )),
+                        end,
+                        (Box::new(colon), vec![Some(e1), Some(verb), rhs]),
+                    ))
+                }
+                Some(Spanned(s, e, Token::Builtin(b))) => {
+                    let verb = self.adverbs(ASTNode::Expr(Spanned(s, e, K0::Builtin(b).into())));
+                    self.infix(e1, verb)?
+                }
+                _ => {
+                    let mut noun2 = self.noun_next();
+                    let mut e2 = match self.subexpr()? {
+                        Some(e2) => e2,
+                        None => return Ok(e1),
+                    };
+                    while let Some(Spanned(s, _, _)) = self
+                        .tokens_iter
+                        .next_if(|x| matches!(x.2, Token::LtBracket))
+                    {
+                        noun2 = true;
+                        e2 = self.brackets(e2, s)?;
+                    }
+                    // a noun modified by adverbs between two nouns is applied infix: 0 f/x
+                    if noun
+                        && noun2
+                        && matches!(
+                            self.tokens_iter.peek(),
+                            Some(Spanned(_, _, Token::Adverb(_)))
+                        )
+                    {
+                        let verb = self.adverbs(e2);
+                        self.infix(e1, verb)?
+                    } else {
+                        let e2 = self.rest(e2, noun2)?;
+                        ASTNode::Apply(Spanned(
+                            e1.start(),
+                            e2.end(),
+                            (Box::new(e1), vec![Some(e2)]),
+                        ))
+                    }
                 }
-            }
-            Some(Spanned(s, _, Token::LtBracket)) => {
-                let Spanned(_, e, exprs) = self.bracket_expr_list(s)?;
-                ASTNode::Apply(Spanned(e1.start(), e, (Box::new(e1), exprs)))
-            }
-            _ => match self.expr()? {
-                Some(e2) => ASTNode::Apply(Spanned(
-                    e1.start(),
-                    e2.end(),
-                    (Box::new(e1), vec![Some(e2)]),
-                )),
-                None => e1,
             },
-        };
-        Ok(Some(res))
+        )
     }
 
-    fn subexpr(&mut self) -> PResult {
-        let Spanned(s, e, t) = match self
+    // the expression on the left of a [ applied to the bracketed list
+    fn brackets(&mut self, e: ASTNode, s: usize) -> Result<ASTNode, ParserError> {
+        let Spanned(_, end, exprs) = self.bracket_expr_list(s)?;
+        Ok(ASTNode::Apply(Spanned(
+            e.start(),
+            end,
+            (Box::new(e), exprs),
+        )))
+    }
+
+    // adverb applied to the expression on its left
+    fn derive(&self, e: ASTNode, Spanned(s, end, a): Spanned<Adverb>) -> ASTNode {
+        let adverb = ASTNode::Expr(Spanned(s, end, K0::Adverb(a).into()));
+        ASTNode::Apply(Spanned(e.start(), end, (Box::new(adverb), vec![Some(e)])))
+    }
+
+    // adverbs following a verb
+    fn adverbs(&mut self, mut e: ASTNode) -> ASTNode {
+        while let Some(Spanned(s, end, Token::Adverb(a))) = self
             .tokens_iter
-            .next_if(|x| !matches!(x.2, Token::Semi | Token::RtParen | Token::RtBracket))
+            .next_if(|x| matches!(x.2, Token::Adverb(_)))
         {
+            e = self.derive(e, Spanned(s, end, a));
+        }
+        e
+    }
+
+    // verb applied to the lhs and the (possibly elided) expression to its right
+    fn infix(&mut self, e1: ASTNode, verb: ASTNode) -> Result<ASTNode, ParserError> {
+        Ok(match self.expr()? {
+            Some(e2) => ASTNode::Apply(Spanned(
+                e1.start(),
+                e2.end(),
+                (Box::new(verb), vec![Some(e1), Some(e2)]),
+            )),
+            None => ASTNode::Apply(Spanned(
+                e1.start(),
+                verb.end(),
+                (Box::new(verb), vec![Some(e1), None]),
+            )),
+        })
+    }
+
+    fn subexpr(&mut self) -> PResult {
+        let Spanned(s, e, t) = match self.tokens_iter.next_if(|x| {
+            !matches!(
+                x.2,
+                Token::Semi | Token::RtParen | Token::RtBracket | Token::RtBrace
+            )
+        }) {
             None => return Ok(None),
             Some(s) => s,
         };
         Ok(Some(match t {
             Token::LtParen => extract_ast!(self.paren(s)),
-            //Token::LtBraces => extract_ast!(self.function(s)),
+            Token::LtBrace => extract_ast!(self.function(s)),
             Token::LtBracket => extract_ast!(self.bracket(s)),
-            Token::Verb(v) => ASTNode::Expr(Spanned(s, e, K0::Verb(v).into())),
+            Token::Verb(v) | Token::MonadicVerb(v) => {
+                ASTNode::Expr(Spanned(s, e, K0::Verb(v).into()))
+            }
             Token::Adverb(a) => ASTNode::Expr(Spanned(s, e, K0::Adverb(a).into())),
+            Token::Builtin(b) => ASTNode::Expr(Spanned(s, e, K0::Builtin(b).into())),
+            Token::Bool(b) => ASTNode::Expr(Spanned(s, e, K0::Bool(b).into())),
             Token::Char(c) => ASTNode::Expr(Spanned(s, e, K0::Char(c).into())),
             Token::Int(i) => ASTNode::Expr(Spanned(s, e, K0::Int(i).into())),
             Token::Float(f) => ASTNode::Expr(Spanned(s, e, K0::Float(f).into())),
             Token::Sym(sym) => ASTNode::Expr(Spanned(s, e, K0::Sym(sym).into())),
-            Token::CharList(c) => ASTNode::Expr(Spanned(s, e, K0::CharList(c).into())),
-            Token::IntList(i) => ASTNode::Expr(Spanned(s, e, K0::IntList(i).into())),
-            Token::FloatList(f) => ASTNode::Expr(Spanned(s, e, K0::FloatList(f).into())),
-            Token::SymList(sym) => ASTNode::Expr(Spanned(s, e, K0::SymList(sym).into())),
+            Token::BoolList(b) => ASTNode::Expr(Spanned(s, e, K0::BoolList(to_list(b)).into())),
+            Token::CharList(c) => ASTNode::Expr(Spanned(s, e, K0::CharList(to_list(c)).into())),
+            Token::IntList(i) => ASTNode::Expr(Spanned(s, e, K0::IntList(to_list(i)).into())),
+            Token::FloatList(f) => ASTNode::Expr(Spanned(s, e, K0::FloatList(to_list(f)).into())),
+            Token::SymList(sym) => ASTNode::Expr(Spanned(s, e, K0::SymList(to_list(sym)).into())),
             Token::Name(id) => ASTNode::Expr(Spanned(s, e, K0::Name(id).into())),
-            _ => ASTNode::Expr(Spanned(0, 0, K0::GenList(vec![]).into())), // replace with error or unreachable..
+            Token::Nil => ASTNode::Expr(Spanned(s, e, K0::Nil.into())),
+            _ => ASTNode::Expr(Spanned(0, 0, K0::GenList(list![]).into())), // replace with error or unreachable..
         }))
     }
 
@@ -179,7 +324,7 @@ impl Parser {
                 1 => Ok(Some(ASTNode::Expr(Spanned(
                     start,
                     end,
-                    K0::GenList(Vec::new()).into(),
+                    K0::GenList(list![]).into(),
                 )))),
                 // list of objects
                 _ => Ok(Some(ASTNode::Apply(Spanned(
@@ -189,7 +334,7 @@ impl Parser {
                         Box::new(ASTNode::Expr(Spanned(
                             start,
                             start,
-                            K0::Verb(Verb::Comma).into(),
+                            K0::Builtin(Builtin::Enlist).into(),
                         ))),
                         exprs,
                     ),
@@ -197,11 +342,72 @@ impl Parser {
             },
             None => Err(ParserError {
                 location: start,
+                end: None,
+                verb: None,
                 code: ParserErrorCode::UnclosedParens,
             }),
         }
     }
 
+    // function with optional parameters and its body, the source text is kept for display
+    fn function(&mut self, start: usize) -> PResult {
+        let params = match self
+            .tokens_iter
+            .next_if(|x| matches!(x.2, Token::LtBracket))
+        {
+            Some(Spanned(s, _, _)) => Some(self.params(s)?),
+            None => None,
+        };
+        let Spanned(_, _, body) = self.expr_list(start)?;
+        match self.tokens_iter.next_if(|x| matches!(x.2, Token::RtBrace)) {
+            Some(Spanned(_, end, _)) => Ok(Some(ASTNode::Func(Spanned(
+                start,
+                end,
+                Func {
+                    src: self.src[start..end].to_vec(),
+                    params,
+                    body,
+                },
+            )))),
+            None => Err(ParserError {
+                location: start,
+                end: None,
+                verb: None,
+                code: ParserErrorCode::UnclosedBraces,
+            }),
+        }
+    }
+
+    // semicolon separated distinct names up to the closing bracket
+    fn params(&mut self, start: usize) -> Result<Vec<Sym>, ParserError> {
+        let mut params = Vec::new();
+        loop {
+            let (location, code) = match self.tokens_iter.next() {
+                Some(Spanned(_, _, Token::RtBracket)) if params.is_empty() => return Ok(params),
+                Some(Spanned(s, _, Token::Name(name))) if params.contains(&name) => {
+                    (s, ParserErrorCode::DuplicateParameter)
+                }
+                Some(Spanned(_, _, Token::Name(name))) => {
+                    params.push(name);
+                    match self.tokens_iter.next() {
+                        Some(Spanned(_, _, Token::Semi)) => continue,
+                        Some(Spanned(_, _, Token::RtBracket)) => return Ok(params),
+                        Some(Spanned(s, _, _)) => (s, ParserErrorCode::UnexpectedToken),
+                        None => (start, ParserErrorCode::UnclosedBrackets),
+                    }
+                }
+                Some(Spanned(s, _, _)) => (s, ParserErrorCode::UnexpectedToken),
+                None => (start, ParserErrorCode::UnclosedBrackets),
+            };
+            return Err(ParserError {
+                location,
+                end: None,
+                verb: None,
+                code,
+            });
+        }
+    }
+
     // bracketed expression list
     fn bracket(&mut self, start: usize) -> PResult {
         Ok(Some(ASTNode::ExprList(self.bracket_expr_list(start)?)))
@@ -219,6 +425,8 @@ impl Parser {
             Some(Spanned(_, end, _)) => Ok(Spanned(start, end, exprs)),
             None => Err(ParserError {
                 location: start,
+                end: None,
+                verb: None,
                 code: ParserErrorCode::UnclosedBrackets,
             }),
         }
@@ -254,3 +462,74 @@ impl Parser {
         ))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{ASTNode, Parser};
+    use crate::error::ParserErrorCode;
+    use crate::span::Spanned;
+    use crate::sym::Sym;
+    use crate::tok::Tokenizer;
+
+    fn parse(src: &[u8]) -> Result<Option<ASTNode>, ParserErrorCode> {
+        let tokens = Tokenizer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+        Parser::new(src, tokens).parse().map_err(|e| e.code)
+    }
+
+    #[test]
+    fn function_test() {
+        for (src, body_len) in [(&b"{x+1}"[..], 1), (b"{}", 1), (b"{a:x;a*{y}[a]}", 2)] {
+            match parse(src).unwrap() {
+                Some(ASTNode::Func(Spanned(0, end, func))) => {
+                    assert_eq!(end, src.len());
+                    assert_eq!(func.src, src);
+                    assert_eq!(func.body.len(), body_len);
+                }
+                ast => panic!("unexpected ast {:?}", ast),
+            }
+        }
+        match parse(b"1+{{x}}").unwrap() {
+            Some(ASTNode::Apply(Spanned(_, _, (_, args)))) => match &args[1] {
+                Some(ASTNode::Func(Spanned(2, 7, func))) => assert_eq!(func.src, b"{{x}}"),
+                ast => panic!("unexpected ast {:?}", ast),
+            },
+            ast => panic!("unexpected ast {:?}", ast),
+        }
+        assert!(matches!(
+            parse(b"{x+1"),
+            Err(ParserErrorCode::UnclosedBraces)
+        ));
+        assert!(matches!(
+            parse(b"{{x}"),
+            Err(ParserErrorCode::UnclosedBraces)
+        ));
+        assert!(matches!(
+            parse(b"x+1}"),
+            Err(ParserErrorCode::UnexpectedToken)
+        ));
+    }
+
+    #[test]
+    fn params_test() {
+        let params = |src| match parse(src).unwrap() {
+            Some(ASTNode::Func(Spanned(_, _, func))) => func.params,
+            ast => panic!("unexpected ast {:?}", ast),
+        };
+        assert_eq!(params(b"{x}"), None);
+        assert_eq!(params(b"{[] 42}"), Some(vec![]));
+        assert_eq!(
+            params(b"{[a;b] a+b}"),
+            Some(vec![Sym::new(b"a"), Sym::new(b"b")])
+        );
+        for (src, code) in [
+            (&b"{[a;a] a}"[..], ParserErrorCode::DuplicateParameter),
+            (b"{[a;1] a}", ParserErrorCode::UnexpectedToken),
+            (b"{[a b] a}", ParserErrorCode::UnexpectedToken),
+            (b"{[a;] a}", ParserErrorCode::UnexpectedToken),
+            (b"{[a", ParserErrorCode::UnclosedBrackets),
+        ] {
+            let err = parse(src).unwrap_err();
+            assert_eq!(std::mem::discriminant(&err), std::mem::discriminant(&code));
+        }
+    }
+}
@@ -1,18 +1,62 @@
 use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::lazy::SyncLazy;
 use std::mem;
 use std::sync::RwLock;
 
-static INTERNER: SyncLazy<RwLock<Interner>> = SyncLazy::new(|| RwLock::new(Interner::new()));
+// the high bits of a symbol pick its shard, the rest index into it
+const SHARD_BITS: u32 = 4;
+const SHARDS: usize = 1 << SHARD_BITS;
+const INDEX_BITS: u32 = u32::BITS - SHARD_BITS;
+
+// names are spread over shards by hash, each with its own lock
+static INTERNER: SyncLazy<[RwLock<Interner>; SHARDS]> =
+    SyncLazy::new(|| [(); SHARDS].map(|_| RwLock::new(Interner::new())));
 
 #[derive(Copy, Clone, Eq, Hash, PartialEq)]
 pub struct Sym(u32);
 
 impl Sym {
+    // for names the interpreter spells itself, which always fit
     pub fn new(string: &[u8]) -> Self {
-        INTERNER.write().expect("poisoned rwlock").intern(string)
+        Self::try_new(string).unwrap_or_else(|| {
+            panic!(
+                "symbol table full: a shard holds at most {} bytes of names",
+                Interner::MAX_SIZE
+            )
+        })
+    }
+
+    // None once the shard the name hashes to is full, for names that come from input
+    pub fn try_new(string: &[u8]) -> Option<Self> {
+        let mut hasher = DefaultHasher::new();
+        string.hash(&mut hasher);
+        let shard = hasher.finish() as usize % SHARDS;
+        // names are mostly interned already, so only a new one takes the write lock
+        let known = INTERNER[shard]
+            .read()
+            .expect("poisoned rwlock")
+            .map
+            .get(string)
+            .copied();
+        let index = match known {
+            Some(index) => index,
+            None => INTERNER[shard]
+                .write()
+                .expect("poisoned rwlock")
+                .intern(string)?,
+        };
+        Some(Sym((shard as u32) << INDEX_BITS | index))
+    }
+
+    pub fn as_bytes(&self) -> &'static [u8] {
+        INTERNER[(self.0 >> INDEX_BITS) as usize]
+            .read()
+            .expect("poisoned rwlock")
+            .lookup(self.0 & ((1 << INDEX_BITS) - 1))
     }
 }
 
@@ -21,8 +65,7 @@ impl Ord for Sym {
         if self.eq(other) {
             return Ordering::Equal;
         }
-        let lock = INTERNER.read().expect("poisoned rwlock");
-        lock.lookup(*self).cmp(lock.lookup(*other))
+        self.as_bytes().cmp(other.as_bytes())
     }
 }
 
@@ -37,7 +80,7 @@ impl fmt::Debug for Sym {
         write!(
             f,
             "`{:?}({})",
-            String::from_utf8_lossy(INTERNER.read().expect("poisoned rwlock").lookup(*self)),
+            String::from_utf8_lossy(self.as_bytes()),
             self.0
         )
     }
@@ -45,61 +88,144 @@ impl fmt::Debug for Sym {
 
 impl fmt::Display for Sym {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "`{}",
-            String::from_utf8_lossy(INTERNER.read().expect("poisoned rwlock").lookup(*self))
-        )
+        write!(f, "`{}", String::from_utf8_lossy(self.as_bytes()))
     }
 }
 
+// one shard of the interned names, indexed from 0
 struct Interner {
-    map: HashMap<&'static [u8], Sym>,
+    map: HashMap<&'static [u8], u32>,
     vec: Vec<&'static [u8]>,
     head: Vec<u8>,
     rest: Vec<Vec<u8>>,
+    // bytes reserved for names so far, never more than max_size. Names are never freed, since
+    // the bytes of every Sym handed out are borrowed for good.
+    size: usize,
+    max_size: usize,
 }
 
 impl Interner {
     const INIT_SIZE: usize = 4096;
+    // per shard, so all names together can take up to SHARDS times this, 1 GiB. A name longer
+    // than this never fits.
     const MAX_SIZE: usize = 64 << 20;
 
     fn new() -> Self {
+        Self::with_max_size(Self::MAX_SIZE)
+    }
+
+    fn with_max_size(max_size: usize) -> Self {
+        let head = Vec::with_capacity(Self::INIT_SIZE.min(max_size));
         Interner {
             map: HashMap::default(),
             vec: Vec::new(),
-            head: Vec::with_capacity(Self::INIT_SIZE),
+            size: head.capacity(),
+            head,
             rest: Vec::new(),
+            max_size,
         }
     }
 
-    fn intern(&mut self, name: &[u8]) -> Sym {
-        if let Some(&sym) = self.map.get(name) {
-            return sym;
+    // None once the shard is full
+    fn intern(&mut self, name: &[u8]) -> Option<u32> {
+        if let Some(&index) = self.map.get(name) {
+            return Some(index);
+        }
+        if self.vec.len() >= 1 << INDEX_BITS {
+            return None;
         }
-        let name: &'static [u8] = unsafe { &*(self.alloc(name) as *const [u8]) };
-        let sym = Sym(self.vec.len() as u32);
-        self.map.insert(name, sym);
+        let name: &'static [u8] = unsafe { &*(self.alloc(name)? as *const [u8]) };
+        let index = self.vec.len() as u32;
+        self.map.insert(name, index);
         self.vec.push(name);
-        sym
+        Some(index)
     }
 
-    fn lookup(&self, id: Sym) -> &'static [u8] {
-        self.vec[id.0 as usize]
+    fn lookup(&self, index: u32) -> &'static [u8] {
+        self.vec[index as usize]
     }
 
-    fn alloc(&mut self, name: &[u8]) -> &[u8] {
+    // a new chunk doubles the last one, cut down to what is left under max_size
+    fn alloc(&mut self, name: &[u8]) -> Option<&[u8]> {
         let cap = self.head.capacity();
         if name.len() > cap - self.head.len() {
-            let new_cap = (cap.min(Self::MAX_SIZE / 2).max(name.len()) + 1)
+            let left = self.max_size - self.size;
+            let new_cap = (cap.min(self.max_size / 2).max(name.len()) + 1)
                 .checked_next_power_of_two()
-                .expect("capacity overflow");
+                .map_or(left, |n| n.min(left));
+            if new_cap < name.len() {
+                return None;
+            }
             let old_head = mem::replace(&mut self.head, Vec::with_capacity(new_cap));
+            self.size += self.head.capacity();
             self.rest.push(old_head);
         }
 
         let start = self.head.len();
         self.head.extend_from_slice(name);
-        &self.head[start..]
+        Some(&self.head[start..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::{Interner, Sym};
+
+    #[test]
+    fn concurrent_intern_test() {
+        let names = (0..2000)
+            .map(|i| format!("stress{}", i).into_bytes())
+            .collect::<Vec<_>>();
+        let names = &names;
+        // every thread interns every name, in a different order
+        let syms = thread::scope(|s| {
+            let workers = (0..8)
+                .map(|t| {
+                    s.spawn(move || {
+                        let mut syms = vec![None; names.len()];
+                        for k in 0..names.len() {
+                            let i = (k * 7 + t * 251) % names.len();
+                            syms[i] = Some(Sym::new(&names[i]));
+                        }
+                        syms.into_iter().map(Option::unwrap).collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .map(|w| w.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        for other in &syms[1..] {
+            assert!(syms[0] == *other);
+        }
+        for (name, sym) in names.iter().zip(&syms[0]) {
+            assert_eq!(sym.as_bytes(), &name[..]);
+            assert!(*sym == Sym::new(name));
+        }
+        let mut distinct = syms[0].clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), names.len());
+    }
+
+    #[test]
+    fn max_size_test() {
+        let max_size = Interner::INIT_SIZE * 3;
+        let mut interner = Interner::with_max_size(max_size);
+        let name = |i: usize| format!("{:0>100}", i).into_bytes();
+        let mut n = 0;
+        while let Some(index) = interner.intern(&name(n)) {
+            assert_eq!(index as usize, n);
+            n += 1;
+        }
+        assert!(interner.size <= max_size);
+        assert!(n * 100 > max_size - Interner::INIT_SIZE);
+        // known names are still found, new ones too long for what is left are not added
+        assert_eq!(interner.intern(&name(0)), Some(0));
+        assert_eq!(interner.lookup(n as u32 - 1), &name(n - 1)[..]);
+        assert_eq!(interner.intern(&vec![b'y'; max_size * 2]), None);
     }
 }
@@ -1,7 +1,7 @@
 use std::str;
 
 use crate::error::{LexerError, LexerErrorCode};
-use crate::k::{Adverb, Verb};
+use crate::k::{Adverb, Builtin, Verb, INF_INT, NULL_INT};
 use crate::span::Spanned;
 use crate::sym::Sym;
 use crate::tok::stream::ByteStream;
@@ -19,19 +19,37 @@ pub enum Token {
     Semi,
 
     Verb(Verb),
+    // x+:y assigns x+y to x
+    ModAssign(Verb),
+    // -: anywhere else is - used monadically
+    MonadicVerb(Verb),
     Adverb(Adverb),
+    Builtin(Builtin),
 
+    Bool(bool),
     Char(u8),
     Int(i64),
     Float(f64),
     Sym(Sym),
 
+    BoolList(Vec<bool>),
     CharList(Vec<u8>),
     IntList(Vec<i64>),
     FloatList(Vec<f64>),
     SymList(Vec<Sym>),
 
     Name(Sym),
+    Nil,
+}
+
+impl From<Vec<bool>> for Token {
+    fn from(mut v: Vec<bool>) -> Self {
+        if v.len() == 1 {
+            Token::Bool(v.remove(0))
+        } else {
+            Token::BoolList(v)
+        }
+    }
 }
 
 impl From<Vec<u8>> for Token {
@@ -74,9 +92,29 @@ impl From<Vec<Sym>> for Token {
     }
 }
 
+fn parse_int(x: &[u8]) -> Result<i64, LexerErrorCode> {
+    match x {
+        b"0N" | b"-0N" => Ok(NULL_INT),
+        b"0W" => Ok(INF_INT),
+        b"-0W" => Ok(-INF_INT),
+        _ => Ok(unsafe { str::from_utf8_unchecked(x) }.parse()?),
+    }
+}
+
+fn parse_float(x: &[u8]) -> Result<f64, LexerErrorCode> {
+    match x {
+        b"0N" | b"-0N" | b"0n" | b"-0n" => Ok(f64::NAN),
+        b"0W" | b"0w" => Ok(f64::INFINITY),
+        b"-0W" | b"-0w" => Ok(f64::NEG_INFINITY),
+        _ => Ok(unsafe { str::from_utf8_unchecked(x) }.parse()?),
+    }
+}
+
 pub struct Tokenizer<'a> {
     stream: ByteStream<'a>,
     start: usize,
+    // whether the last token was something a verb-colon pair can assign to, like x or x[i]
+    assignable: bool,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -84,6 +122,7 @@ impl<'a> Tokenizer<'a> {
         Self {
             stream: ByteStream::new(src),
             start: 0,
+            assignable: false,
         }
     }
 
@@ -95,9 +134,21 @@ impl<'a> Tokenizer<'a> {
         Some(Ok(Spanned(self.start, self.stream.next_index(), token)))
     }
 
+    // a verb, or when a colon follows a modified assignment like x+: after a name, else the
+    // monadic form of the verb like -:
+    fn verb(&mut self, verb: Verb) -> Option<<Self as Iterator>::Item> {
+        match self.stream.next_if_eq(b':') {
+            Some(_) if self.assignable => self.token(Token::ModAssign(verb)),
+            Some(_) => self.token(Token::MonadicVerb(verb)),
+            None => self.token(Token::Verb(verb)),
+        }
+    }
+
     fn error(&self, error: LexerErrorCode) -> Option<<Self as Iterator>::Item> {
         Some(Err(LexerError {
             location: self.start,
+            end: None,
+            verb: None,
             code: error,
         }))
     }
@@ -114,7 +165,10 @@ impl<'a> Tokenizer<'a> {
             let start = self.stream.next_index();
             self.stream
                 .consume_while(|x| x.is_ascii_alphanumeric() || matches!(x, b'.' | b':'));
-            syms.push(Sym::new(self.stream.slice(start)));
+            match Sym::try_new(self.stream.slice(start)) {
+                Some(sym) => syms.push(sym),
+                None => return self.error(LexerErrorCode::SymbolTableFull),
+            }
             if self.stream.next_if_eq(b'`').is_none() {
                 break;
             }
@@ -144,9 +198,21 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    // [a-zA-Z.][a-zA-Z0-9.]*
     fn identifier(&mut self) -> Option<<Self as Iterator>::Item> {
-        self.stream.consume_while(|x| x.is_ascii_alphanumeric());
-        self.token(Token::Name(Sym::new(self.stream.slice(self.start))))
+        self.stream
+            .consume_while(|x| x.is_ascii_alphanumeric() || x == b'.');
+        let name = self.stream.slice(self.start);
+        match name {
+            b"nil" => self.token(Token::Nil),
+            _ => match Builtin::from_name(name) {
+                Some(b) => self.token(Token::Builtin(b)),
+                None => match Sym::try_new(name) {
+                    Some(sym) => self.token(Token::Name(sym)),
+                    None => self.error(LexerErrorCode::SymbolTableFull),
+                },
+            },
+        }
     }
 
     fn skip_whitespace(&mut self) {
@@ -160,6 +226,27 @@ impl<'a> Tokenizer<'a> {
         self.token(Token::Semi)
     }
 
+    // 0x([0-9a-fA-F][0-9a-fA-F])+ bytes, a char for one and a string for more
+    fn hex(&mut self) -> Option<<Self as Iterator>::Item> {
+        let start = self.stream.next_index();
+        self.stream.consume_while(|x| x.is_ascii_hexdigit());
+        let digits = self.stream.slice(start);
+        if digits.is_empty()
+            || digits.len() & 1 == 1
+            || matches!(self.stream.peek(), Some(b'.' | b'a'..=b'z' | b'A'..=b'Z'))
+        {
+            return self.error(LexerErrorCode::InvalidNumber);
+        }
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| {
+                u8::from_str_radix(unsafe { str::from_utf8_unchecked(pair) }, 16)
+                    .expect("hex digits")
+            })
+            .collect::<Vec<_>>();
+        self.token(bytes.into())
+    }
+
     fn is_num_start(&self) -> bool {
         match self.stream.peek() {
             Some(b'0'..=b'9') => true,
@@ -169,29 +256,66 @@ impl<'a> Tokenizer<'a> {
     }
 
     // ([^)}\]0-9a-zA-Z]-)?([0-9]+(\.[0-9]*)?|\.[0-9]+)(e[-+]?[0-9]+)?( -?([0-9]+(\.[0-9]*)?|\.[0-9]+)(e[-+]?[0-9]+)?)*
-    // todo - handle infinities/nulls
+    // or one of the nulls/infinities 0N 0W 0n 0w in place of a number, or [01]+b for bools
     fn number(&mut self) -> Option<<Self as Iterator>::Item> {
+        if self.stream.slice(self.start) == b"0" && self.stream.next_if_eq(b'x').is_some() {
+            return self.hex();
+        }
         let mut is_float = false;
         let mut start = self.start;
         loop {
+            let mut special = None;
             if self.stream.curr() == Some(b'.') {
                 is_float = true;
             } else {
                 self.stream.consume_while(|x| x.is_ascii_digit());
-                is_float |= self.stream.next_if_eq(b'.').is_some();
+                if matches!(self.stream.slice(start), b"0" | b"-0") {
+                    special = self
+                        .stream
+                        .next_if(|x| matches!(x, b'N' | b'W' | b'n' | b'w'));
+                }
             }
-            // digits before decimal point are consumed at this point
-            self.stream.consume_while(|x| x.is_ascii_digit());
-            if self.stream.next_if_eq(b'e').is_some() {
-                is_float = true;
-                self.stream.next_if(|x| matches!(x, b'+' | b'-'));
-                if self.stream.consume_while(|x| x.is_ascii_digit()) == 0 {
-                    self.start = start;
-                    return self.error(LexerErrorCode::UnterminatedFloatExponent);
+            match special {
+                Some(c) => is_float |= matches!(c, b'n' | b'w'),
+                None => {
+                    is_float |= self.stream.next_if_eq(b'.').is_some();
+                    // digits before decimal point are consumed at this point
+                    self.stream.consume_while(|x| x.is_ascii_digit());
+                    if self.stream.next_if_eq(b'e').is_some() {
+                        is_float = true;
+                        self.stream.next_if(|x| matches!(x, b'+' | b'-'));
+                        if self.stream.consume_while(|x| x.is_ascii_digit()) == 0 {
+                            self.start = start;
+                            return self.error(LexerErrorCode::UnterminatedFloatExponent);
+                        }
+                    }
                 }
             }
             let backtrack = self.stream.clone();
             match self.stream.peek() {
+                // a run of 0s and 1s followed by b is a bool or a list of bools
+                Some(b'b')
+                    if self
+                        .stream
+                        .slice(self.start)
+                        .iter()
+                        .all(|x| matches!(x, b'0' | b'1')) =>
+                {
+                    let bits = self
+                        .stream
+                        .slice(self.start)
+                        .iter()
+                        .map(|&x| x == b'1')
+                        .collect::<Vec<_>>();
+                    self.stream.next(); // 'b'
+                    if matches!(
+                        self.stream.peek(),
+                        Some(b'.' | b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z')
+                    ) {
+                        return self.error(LexerErrorCode::InvalidNumber);
+                    }
+                    return self.token(bits.into());
+                }
                 Some(b' ') => {
                     self.stream.next(); // ' '
                     start = self.stream.next_index();
@@ -201,7 +325,8 @@ impl<'a> Tokenizer<'a> {
                         break;
                     }
                 }
-                Some(b'.' | b'a'..=b'z' | b'A'..=b'Z') => {
+                // digits can only follow one of the nulls or infinities here, as in 0N0W
+                Some(b'.' | b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z') => {
                     self.start = start;
                     return self.error(LexerErrorCode::InvalidNumber);
                 }
@@ -209,19 +334,19 @@ impl<'a> Tokenizer<'a> {
             }
         }
         macro_rules! parse_nums {
-            ($ty: ty, $lexeme: ident) => {
+            ($parse: ident, $lexeme: ident) => {
                 $lexeme
                     .split(|&x| x == b' ')
-                    .map(|x| unsafe { str::from_utf8_unchecked(x) }.parse())
-                    .collect::<Result<Vec<$ty>, _>>()
-                    .map_or_else(|e| self.error(e.into()), |v| self.token(v.into()))
+                    .map($parse)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_or_else(|e| self.error(e), |v| self.token(v.into()))
             };
         }
         let slice = self.stream.slice(self.start);
         if is_float {
-            parse_nums!(f64, slice)
+            parse_nums!(parse_float, slice)
         } else {
-            parse_nums!(i64, slice)
+            parse_nums!(parse_int, slice)
         }
     }
 }
@@ -239,8 +364,11 @@ impl Iterator for Tokenizer<'_> {
                 b'[' => self.token(Token::LtBracket),
                 b']' => self.token(Token::RtBracket),
                 b';' => self.token(Token::Semi),
+                b':' if self.stream.next_if_eq(b':').is_some() => {
+                    self.token(Token::Verb(Verb::ColonColon))
+                }
                 b':' => self.token(Token::Verb(Verb::Colon)),
-                b'+' => self.token(Token::Verb(Verb::Plus)),
+                b'+' => self.verb(Verb::Plus),
                 b'-' => {
                     if (!matches!(
                         self.stream.prev(),
@@ -249,29 +377,37 @@ impl Iterator for Tokenizer<'_> {
                     {
                         self.number() // -.[0-9] or -[0-9]
                     } else {
-                        self.token(Token::Verb(Verb::Minus))
+                        self.verb(Verb::Minus)
                     }
                 }
-                b'*' => self.token(Token::Verb(Verb::Star)),
-                b'%' => self.token(Token::Verb(Verb::Percent)),
-                b'&' => self.token(Token::Verb(Verb::And)),
-                b'|' => self.token(Token::Verb(Verb::Pipe)),
-                b'^' => self.token(Token::Verb(Verb::Caret)),
-                b'=' => self.token(Token::Verb(Verb::Eq)),
-                b'<' => self.token(Token::Verb(Verb::Lt)),
-                b'>' => self.token(Token::Verb(Verb::Gt)),
-                b'$' => self.token(Token::Verb(Verb::Dollar)),
-                b',' => self.token(Token::Verb(Verb::Comma)),
-                b'#' => self.token(Token::Verb(Verb::Hash)),
-                b'_' => self.token(Token::Verb(Verb::Underscore)),
-                b'~' => self.token(Token::Verb(Verb::Tilde)),
-                b'!' => self.token(Token::Verb(Verb::Bang)),
-                b'?' => self.token(Token::Verb(Verb::Question)),
-                b'@' => self.token(Token::Verb(Verb::At)),
+                b'*' => self.verb(Verb::Star),
+                b'%' => self.verb(Verb::Percent),
+                b'&' => self.verb(Verb::And),
+                b'|' => self.verb(Verb::Pipe),
+                b'^' => self.verb(Verb::Caret),
+                b'=' => self.verb(Verb::Eq),
+                b'<' => self.verb(Verb::Lt),
+                b'>' => self.verb(Verb::Gt),
+                b'$' => self.verb(Verb::Dollar),
+                b',' => self.verb(Verb::Comma),
+                b'#' => self.verb(Verb::Hash),
+                b'_' => self.verb(Verb::Underscore),
+                b'~' => self.verb(Verb::Tilde),
+                b'!' => self.verb(Verb::Bang),
+                b'?' => self.verb(Verb::Question),
+                b'@' => self.verb(Verb::At),
                 b'.' if self.stream.peek().filter(|x| x.is_ascii_digit()).is_some() => {
                     self.number() // .[0-9]
                 }
-                b'.' => self.token(Token::Verb(Verb::Dot)),
+                b'.' if self
+                    .stream
+                    .peek()
+                    .filter(|x| x.is_ascii_alphabetic())
+                    .is_some() =>
+                {
+                    self.identifier() // .ctx.name
+                }
+                b'.' => self.verb(Verb::Dot),
                 b'0' if self.stream.next_if_eq(b':').is_some() => {
                     self.token(Token::Verb(Verb::ZeroColon))
                 }
@@ -314,7 +450,170 @@ impl Iterator for Tokenizer<'_> {
                 _ => self.error(LexerErrorCode::UnrecognizedToken),
             };
             self.bump();
+            self.assignable = matches!(
+                tok,
+                Some(Ok(Spanned(_, _, Token::Name(_) | Token::RtBracket)))
+            );
             break tok;
         }
     }
 }
+
+// more lines are needed before src can be parsed: it has unclosed brackets or an unterminated
+// string
+pub fn is_incomplete(src: &[u8]) -> bool {
+    let mut depth = 0i64;
+    for tok in Tokenizer::new(src) {
+        match tok {
+            Ok(Spanned(_, _, Token::LtParen | Token::LtBrace | Token::LtBracket)) => depth += 1,
+            Ok(Spanned(_, _, Token::RtParen | Token::RtBrace | Token::RtBracket)) => depth -= 1,
+            Ok(_) => {}
+            Err(e) => return matches!(e.code, LexerErrorCode::UnterminatedString),
+        }
+    }
+    depth > 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_incomplete, Token, Tokenizer};
+    use crate::error::LexerErrorCode;
+
+    // debug names of the tokens, with names shown as text rather than interned ids
+    fn tokens(src: &[u8]) -> Vec<String> {
+        Tokenizer::new(src)
+            .map(|t| match t.unwrap().2 {
+                Token::Name(name) => format!("Name({})", name),
+                t => format!("{:?}", t),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn colon_test() {
+        assert_eq!(tokens(b"a:1"), ["Name(`a)", "Verb(Colon)", "Int(1)"]);
+        assert_eq!(tokens(b"a::1"), ["Name(`a)", "Verb(ColonColon)", "Int(1)"]);
+        assert_eq!(tokens(b"::"), ["Verb(ColonColon)"]);
+        assert_eq!(tokens(b":::"), ["Verb(ColonColon)", "Verb(Colon)"]);
+        assert_eq!(tokens(b"0:"), ["Verb(ZeroColon)"]);
+        assert_eq!(tokens(b"1:x"), ["Verb(OneColon)", "Name(`x)"]);
+        assert_eq!(tokens(b"2: x"), ["Verb(TwoColon)", "Name(`x)"]);
+        assert_eq!(
+            tokens(b"a:10:"),
+            ["Name(`a)", "Verb(Colon)", "Int(10)", "Verb(Colon)"]
+        );
+        assert_eq!(
+            tokens(b"f':x"),
+            ["Name(`f)", "Adverb(QuoteColon)", "Name(`x)"]
+        );
+        assert_eq!(
+            tokens(b"f/:x"),
+            ["Name(`f)", "Adverb(SlashColon)", "Name(`x)"]
+        );
+        assert_eq!(
+            tokens(b"f\\:x"),
+            ["Name(`f)", "Adverb(BackslashColon)", "Name(`x)"]
+        );
+        assert_eq!(tokens(b"f'x"), ["Name(`f)", "Adverb(Quote)", "Name(`x)"]);
+        assert_eq!(tokens(b"a:"), ["Name(`a)", "Verb(Colon)"]);
+        assert_eq!(tokens(b"x+:1"), ["Name(`x)", "ModAssign(Plus)", "Int(1)"]);
+        assert_eq!(
+            tokens(b"x,:\"a\""),
+            ["Name(`x)", "ModAssign(Comma)", "Char(97)"]
+        );
+        assert_eq!(tokens(b"x-:1"), ["Name(`x)", "ModAssign(Minus)", "Int(1)"]);
+        assert_eq!(tokens(b"x:-1"), ["Name(`x)", "Verb(Colon)", "Int(-1)"]);
+        assert_eq!(tokens(b":"), ["Verb(Colon)"]);
+        assert_eq!(
+            tokens(b"x[0]+:1"),
+            [
+                "Name(`x)",
+                "LtBracket",
+                "Int(0)",
+                "RtBracket",
+                "ModAssign(Plus)",
+                "Int(1)"
+            ]
+        );
+        // without a name or index before it, a verb-colon pair is the monadic verb
+        assert_eq!(tokens(b"-:x"), ["MonadicVerb(Minus)", "Name(`x)"]);
+        assert_eq!(
+            tokens(b"f:#:"),
+            ["Name(`f)", "Verb(Colon)", "MonadicVerb(Hash)"]
+        );
+        assert_eq!(
+            tokens(b"#:'x"),
+            ["MonadicVerb(Hash)", "Adverb(Quote)", "Name(`x)"]
+        );
+        assert_eq!(
+            tokens(b"1 -:x"),
+            ["Int(1)", "MonadicVerb(Minus)", "Name(`x)"]
+        );
+    }
+
+    #[test]
+    fn bool_test() {
+        assert_eq!(tokens(b"1b"), ["Bool(true)"]);
+        assert_eq!(tokens(b"0b"), ["Bool(false)"]);
+        assert_eq!(tokens(b"1010b"), ["BoolList([true, false, true, false])"]);
+        assert_eq!(
+            tokens(b"01b+1"),
+            ["BoolList([false, true])", "Verb(Plus)", "Int(1)"]
+        );
+        assert_eq!(tokens(b"1 0"), ["IntList([1, 0])"]);
+        for src in [&b"12b"[..], b"10bx", b"1 0b", b"1.0b", b"-1b"] {
+            assert!(Tokenizer::new(src).any(|t| t.is_err()));
+        }
+    }
+
+    #[test]
+    fn null_inf_test() {
+        assert_eq!(tokens(b"0N"), ["Int(-9223372036854775808)"]);
+        assert_eq!(tokens(b"-0N"), ["Int(-9223372036854775808)"]);
+        assert_eq!(tokens(b"0W"), ["Int(9223372036854775807)"]);
+        assert_eq!(tokens(b"-0W"), ["Int(-9223372036854775807)"]);
+        assert_eq!(tokens(b"0n"), ["Float(NaN)"]);
+        assert_eq!(tokens(b"-0n"), ["Float(NaN)"]);
+        assert_eq!(tokens(b"0w"), ["Float(inf)"]);
+        assert_eq!(tokens(b"-0w"), ["Float(-inf)"]);
+        assert_eq!(
+            tokens(b"1 0N -0W"),
+            ["IntList([1, -9223372036854775808, -9223372036854775807])"]
+        );
+        assert_eq!(tokens(b"0N 0w"), ["FloatList([NaN, inf])"]);
+        assert_eq!(tokens(b"x:0n"), ["Name(`x)", "Verb(Colon)", "Float(NaN)"]);
+        assert_eq!(tokens(b"0n+n"), ["Float(NaN)", "Verb(Plus)", "Name(`n)"]);
+        assert_eq!(tokens(b"n"), ["Name(`n)"]);
+        for src in [&b"0N0W"[..], b"0n1", b"0Nx", b"0w.5", b"00N"] {
+            assert!(Tokenizer::new(src).any(|t| t.is_err()));
+        }
+    }
+
+    #[test]
+    fn hex_test() {
+        assert_eq!(tokens(b"0xff"), ["Char(255)"]);
+        assert_eq!(tokens(b"0xdeadbeef"), ["CharList([222, 173, 190, 239])"]);
+        assert_eq!(
+            tokens(b"0x00FF,1"),
+            ["CharList([0, 255])", "Verb(Comma)", "Int(1)"]
+        );
+        for src in [&b"0x"[..], b"0xfff", b"0xfg", b"0x0.1", b"10xff"] {
+            let err = Tokenizer::new(src).find_map(Result::err).unwrap();
+            assert!(matches!(err.code, LexerErrorCode::InvalidNumber));
+            assert_eq!(err.location, 0);
+        }
+    }
+
+    #[test]
+    fn incomplete_test() {
+        assert!(is_incomplete(b"f:{[a]"));
+        assert!(is_incomplete(b"f:{[a]\n  b:a*2;"));
+        assert!(!is_incomplete(b"f:{[a]\n  b:a*2;\n  b+1}"));
+        assert!(is_incomplete(b"(1;2;[3"));
+        assert!(!is_incomplete(b"1+2)"));
+        assert!(is_incomplete(b"s:\"two\nli"));
+        assert!(!is_incomplete(b"s:\"two\nlines\""));
+        assert!(!is_incomplete(b"s:\"(\""));
+        assert!(!is_incomplete(b"1 / a comment ("));
+    }
+}
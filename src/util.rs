@@ -12,3 +12,16 @@ impl<T: Deref<Target = [u8]>> TrimEnd for T {
             .map_or(0, |x| x + 1)]
     }
 }
+
+pub trait TrimStart {
+    fn trim_start(&self) -> &[u8];
+}
+
+impl<T: Deref<Target = [u8]>> TrimStart for T {
+    fn trim_start(&self) -> &[u8] {
+        &self[self
+            .iter()
+            .position(|x| !x.is_ascii_whitespace())
+            .unwrap_or_else(|| self.len())..]
+    }
+}
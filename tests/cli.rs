@@ -0,0 +1,84 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{self, Command, Output, Stdio};
+
+// run the ak binary with the given arguments and stdin
+fn ak(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ak"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn load_script_test() {
+    let path = env::temp_dir().join(format!("ak-load-test-{}.k", process::id()));
+    fs::write(&path, "loadf:{x*loadn}\nloadn:3\n").unwrap();
+    let output = ak(&[], &format!("\\l {}\nloadf 1 2\n", path.display()));
+    assert_eq!(stdout(&output), "3\n3 6\n");
+    // a script's trailing newline is not an empty last expression, and piped output has no banner
+    fs::write(&path, "1+1\n").unwrap();
+    assert_eq!(stdout(&ak(&[path.to_str().unwrap()], "")), "2\n");
+    fs::remove_file(&path).unwrap();
+    // a missing file is reported and the session goes on
+    let output = ak(&[], "\\l /nonexistent/ak.k\n1+2\n");
+    assert!(stdout(&output).ends_with("\n3\n"));
+}
+
+#[test]
+fn one_liner_test() {
+    let output = ak(&["-e", "1+2"], "");
+    assert_eq!(stdout(&output), "3\n");
+    assert!(output.status.success());
+    // errors go to stderr with a failing exit status
+    let output = ak(&["-e", "1+`a"], "");
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).starts_with("runtime error: "));
+    assert_eq!(output.status.code(), Some(1));
+    let output = ak(&["-"], "x:2\nx*3\n");
+    assert_eq!(stdout(&output), "6\n");
+    assert!(output.status.success());
+    assert_eq!(ak(&["-"], "1+(2\n").status.code(), Some(1));
+}
+
+#[test]
+fn recursion_limit_test() {
+    // runaway recursion fails with an error instead of overflowing the stack
+    let output = ak(&["-e", "f:{$[x=0;0;1+f x-1]}; f 3000"], "");
+    assert!(stderr(&output).contains("Stack"));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&ak(&["-e", "f:{$[x=0;0;1+f x-1]}; f 900"], "")), "900\n");
+}
+
+#[test]
+fn time_command_test() {
+    // \t prints the milliseconds taken, not the result
+    let output = ak(&[], "\\t 1+2\n\\t:100 +/!1000\n");
+    let times = stdout(&output);
+    assert_eq!(times.lines().count(), 2);
+    assert!(times.lines().all(|line| line.parse::<u128>().is_ok()));
+    assert_eq!(stderr(&output), "");
+    // a bad count or a bad expression is reported on stderr and the session goes on
+    let output = ak(&[], "\\t:abc 1+2\n\\t 1+(2\n3*4\n");
+    assert_eq!(stdout(&output), "12\n");
+    assert!(stderr(&output).starts_with("invalid count: abc\nparsing error: "));
+}
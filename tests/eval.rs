@@ -0,0 +1,46 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use ak::error::{EvalError, LexerErrorCode, ParserErrorCode, RuntimeErrorCode};
+use ak::eval;
+use ak::k::K0;
+
+#[test]
+fn eval_test() {
+    assert!(matches!(*eval(b"1+2").unwrap(), K0::Int(3)));
+    assert_eq!(eval(b"evala:2; evala*1 2 3").unwrap().to_string(), "2 4 6");
+    assert!(matches!(*eval(b"").unwrap(), K0::Nil));
+    assert!(matches!(*eval(b"/ a comment").unwrap(), K0::Nil));
+    eval(b"evalf:{[a]\n  b:a*2;\n  b+1}").unwrap();
+    assert_eq!(eval(b"evalf 5").unwrap().to_string(), "11");
+}
+
+#[test]
+fn eval_error_test() {
+    let err = eval(b"1+\"a").unwrap_err();
+    assert!(matches!(
+        err,
+        EvalError::Lexer(ref e) if matches!(e.code, LexerErrorCode::UnterminatedString)
+    ));
+    assert_eq!(err.location(), 2);
+    let err = eval(b"1+(2").unwrap_err();
+    assert!(matches!(
+        err,
+        EvalError::Parser(ref e) if matches!(e.code, ParserErrorCode::UnclosedParens)
+    ));
+    let err = eval(b"1 2+`a").unwrap_err();
+    assert!(matches!(
+        err,
+        EvalError::Runtime(ref e) if matches!(e.code, RuntimeErrorCode::NonNumeric)
+    ));
+}
+
+#[test]
+fn eval_script_test() {
+    let path = env::temp_dir().join(format!("ak-script-test-{}.k", process::id()));
+    fs::write(&path, b"scriptf:{x*scriptn}\nscriptn:3\n").unwrap();
+    eval(&fs::read(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(eval(b"scriptf 1 2").unwrap().to_string(), "3 6");
+}